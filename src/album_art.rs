@@ -0,0 +1,576 @@
+use crate::config::{ArtFit, ArtSource};
+use crate::palette::{self, Palette};
+use crate::placeholder;
+use ggez::graphics::Rect;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::FilterType;
+use image::{
+    AnimationDecoder, DynamicImage, Frames, ImageDecoder, ImageFormat, ImageReader, RgbaImage,
+};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+const BACKDROP_SIZE: u32 = 128;
+/// Like web browsers, frames with shorter delays than this are shown for
+/// DEFAULT_FRAME_DELAY instead, as many GIFs rely on it.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// What the worker thread needs to know to prepare the album art.
+#[derive(Debug, Clone)]
+pub struct DecodeSettings {
+    /// The format from the type MPD reported or the file extension, used if
+    /// the data isn't recognized.
+    pub format_hint: Option<ImageFormat>,
+    pub fit: ArtFit,
+    /// The area the art is fitted into, which limits its size.
+    pub area: Rect,
+    pub max_size: Option<u32>,
+    /// The blur and brightness percentages of the backdrop, if one is drawn.
+    pub backdrop: Option<(f32, u8)>,
+    pub extract_palette: bool,
+    /// How many bytes the frames of animated art may take, or None to only
+    /// decode the first frame.
+    pub animation_memory_limit: Option<usize>,
+    /// The largest side of the window icon made from the art, if one is made.
+    pub icon_size: Option<u32>,
+}
+
+pub struct DecodedArt {
+    /// The first frame of animated art.
+    pub image: RgbaImage,
+    /// Every frame with its delay if the art is animated, otherwise empty.
+    pub frames: Vec<(RgbaImage, Duration)>,
+    /// Set if the art is animated, but only its first frame is shown because
+    /// of the memory limit.
+    pub is_animation_too_large: bool,
+    pub backdrop: Option<RgbaImage>,
+    pub palette: Option<Palette>,
+    pub icon: Option<RgbaImage>,
+    pub is_downscaled: bool,
+}
+
+enum Animation {
+    Still,
+    TooLarge,
+    Frames {
+        frames: Vec<(RgbaImage, Duration)>,
+        is_downscaled: bool,
+    },
+}
+
+/// MPD passes on whatever type the file claims, so this is only a hint.
+pub fn format_from_mime_type(mime_type: &str) -> Option<ImageFormat> {
+    match mime_type {
+        // not standard, but written by some taggers
+        "image/jpg" | "JPG" => Some(ImageFormat::Jpeg),
+        _ => ImageFormat::from_mime_type(mime_type),
+    }
+}
+
+/// The format is sniffed from the magic bytes of the data first.
+fn detect_format(data: &[u8], hint: Option<ImageFormat>) -> Result<ImageFormat, String> {
+    let format = image::guess_format(data)
+        .ok()
+        .or(hint)
+        .ok_or_else(|| String::from("unrecognized image format"))?;
+    // AVIF decoding needs the native dav1d library, which is not built in
+    if format == ImageFormat::Avif || !format.reading_enabled() {
+        return Err(format!("{format:?} images are not supported"));
+    }
+    Ok(format)
+}
+
+/// Returns the horizontal and vertical scale that fits the art into the area.
+pub fn art_fit_scale(fit: ArtFit, art_width: f32, art_height: f32, area: &Rect) -> (f32, f32) {
+    let x_ratio = area.w / art_width;
+    let y_ratio = area.h / art_height;
+    match fit {
+        ArtFit::Contain => {
+            let scale = x_ratio.min(y_ratio);
+            (scale, scale)
+        }
+        ArtFit::Cover => {
+            let scale = x_ratio.max(y_ratio);
+            (scale, scale)
+        }
+        ArtFit::Stretch => (x_ratio, y_ratio),
+        ArtFit::Original => (1.0, 1.0),
+        ArtFit::Integer => {
+            let ratio = x_ratio.min(y_ratio);
+            // art larger than the window is shrunk by a whole number instead
+            let scale = if ratio >= 1.0 {
+                ratio.floor()
+            } else {
+                1.0 / (1.0 / ratio).ceil()
+            };
+            (scale, scale)
+        }
+    }
+}
+
+/// Returns how much to shrink the decoded art by, so that it is not larger
+/// than it is drawn or than "max_size". Art that is drawn unscaled or scaled
+/// by whole numbers is only limited by "max_size".
+fn downscale_factor(width: u32, height: u32, settings: &DecodeSettings) -> f32 {
+    let (width, height) = (width as f32, height as f32);
+    let mut factor = match settings.fit {
+        ArtFit::Original | ArtFit::Integer => 1.0,
+        fit => {
+            let (x_scale, y_scale) = art_fit_scale(fit, width, height, &settings.area);
+            x_scale.max(y_scale).min(1.0)
+        }
+    };
+    if let Some(max_size) = settings.max_size {
+        factor = factor.min(max_size as f32 / width.max(height));
+    }
+    factor
+}
+
+/// The art is shrunk before blurring, since the backdrop is blurry anyway
+/// and blurring the full size image would take too long.
+fn make_backdrop(img: &DynamicImage, blur_percent: f32, brightness_percent: u8) -> RgbaImage {
+    let small = img.thumbnail(BACKDROP_SIZE, BACKDROP_SIZE).to_rgba8();
+    let sigma = blur_percent / 100.0 * small.width().max(small.height()) as f32;
+    let mut backdrop = image::imageops::fast_blur(&small, sigma);
+    for pixel in backdrop.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as u32 * brightness_percent as u32 / 100) as u8;
+        }
+        pixel.0[3] = 255;
+    }
+    backdrop
+}
+
+fn frame_delay(delay: image::Delay) -> Duration {
+    let (numer, denom) = delay.numer_denom_ms();
+    let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0);
+    if delay < MIN_FRAME_DELAY {
+        DEFAULT_FRAME_DELAY
+    } else {
+        delay
+    }
+}
+
+fn animation_frames(data: &[u8], format: ImageFormat) -> image::ImageResult<Option<Frames<'_>>> {
+    match format {
+        ImageFormat::Gif => Ok(Some(GifDecoder::new(Cursor::new(data))?.into_frames())),
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(data))?;
+            Ok(decoder.has_animation().then(|| decoder.into_frames()))
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(data))?;
+            if decoder.is_apng()? {
+                Ok(Some(decoder.apng()?.into_frames()))
+            } else {
+                Ok(None)
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Decodes every frame of animated GIF, WebP and APNG art, shrinking them
+/// like still art. Frames are decoded one at a time, so that the memory
+/// limit is noticed before the whole animation is in memory.
+fn decode_animation(
+    data: &[u8],
+    format: ImageFormat,
+    settings: &DecodeSettings,
+    memory_limit: usize,
+) -> Result<Animation, String> {
+    let Some(frames) =
+        animation_frames(data, format).map_err(|e| format!("invalid {format:?} image: {e}"))?
+    else {
+        return Ok(Animation::Still);
+    };
+    let mut decoded = Vec::new();
+    let mut memory = 0;
+    let mut is_downscaled = false;
+    for frame in frames {
+        let frame = frame.map_err(|e| format!("invalid {format:?} animation frame: {e}"))?;
+        let delay = frame_delay(frame.delay());
+        let mut buffer = frame.into_buffer();
+        let factor = downscale_factor(buffer.width(), buffer.height(), settings);
+        if factor < 1.0 {
+            is_downscaled = true;
+            let width = ((buffer.width() as f32 * factor).round() as u32).max(1);
+            let height = ((buffer.height() as f32 * factor).round() as u32).max(1);
+            buffer = image::imageops::resize(&buffer, width, height, FilterType::Lanczos3);
+        }
+        memory += buffer.as_raw().len();
+        if memory > memory_limit {
+            return Ok(Animation::TooLarge);
+        }
+        decoded.push((buffer, delay));
+    }
+    if decoded.len() > 1 {
+        Ok(Animation::Frames {
+            frames: decoded,
+            is_downscaled,
+        })
+    } else {
+        Ok(Animation::Still)
+    }
+}
+
+/// Decodes the art, applying its EXIF orientation and shrinking it to the
+/// size it is drawn at.
+pub fn decode(data: &[u8], settings: &DecodeSettings) -> Result<DecodedArt, String> {
+    let format = detect_format(data, settings.format_hint)?;
+    let animation = match settings.animation_memory_limit {
+        Some(memory_limit) => decode_animation(data, format, settings, memory_limit)?,
+        None => Animation::Still,
+    };
+    if let Animation::Frames {
+        frames,
+        is_downscaled,
+    } = animation
+    {
+        // animated formats don't carry an EXIF orientation in practice
+        let img = DynamicImage::ImageRgba8(frames[0].0.clone());
+        let (backdrop, palette, icon) = derive_extras(&img, settings);
+        return Ok(DecodedArt {
+            backdrop,
+            palette,
+            icon,
+            image: img.to_rgba8(),
+            is_downscaled,
+            frames,
+            is_animation_too_large: false,
+        });
+    }
+    let is_animation_too_large = matches!(animation, Animation::TooLarge);
+
+    let mut decoder = ImageReader::with_format(Cursor::new(data), format)
+        .into_decoder()
+        .map_err(|e| format!("invalid {format:?} image: {e}"))?;
+    let orientation = decoder
+        .orientation()
+        .map_err(|e| format!("invalid {format:?} orientation: {e}"))?;
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("invalid {format:?} image: {e}"))?;
+    img.apply_orientation(orientation);
+
+    let mut decoded = prepare_still(img, settings);
+    decoded.is_animation_too_large = is_animation_too_large;
+    Ok(decoded)
+}
+
+/// Shrinks still art to the size it is drawn at and derives what else is
+/// wanted from it.
+fn prepare_still(mut img: DynamicImage, settings: &DecodeSettings) -> DecodedArt {
+    let factor = downscale_factor(img.width(), img.height(), settings);
+    let is_downscaled = factor < 1.0;
+    if is_downscaled {
+        let width = ((img.width() as f32 * factor).round() as u32).max(1);
+        let height = ((img.height() as f32 * factor).round() as u32).max(1);
+        img = img.resize_exact(width, height, FilterType::Lanczos3);
+    }
+
+    let (backdrop, palette, icon) = derive_extras(&img, settings);
+    DecodedArt {
+        backdrop,
+        palette,
+        icon,
+        image: img.to_rgba8(),
+        frames: Vec::new(),
+        is_animation_too_large: false,
+        is_downscaled,
+    }
+}
+
+/// The backdrop, palette and window icon the settings ask for, derived from
+/// the art as it is drawn (the first frame of animated art).
+fn derive_extras(
+    img: &DynamicImage,
+    settings: &DecodeSettings,
+) -> (Option<RgbaImage>, Option<Palette>, Option<RgbaImage>) {
+    let backdrop = settings
+        .backdrop
+        .map(|(blur, brightness)| make_backdrop(img, blur, brightness));
+    let palette = if settings.extract_palette {
+        palette::extract_palette(img)
+    } else {
+        None
+    };
+    let icon = settings
+        .icon_size
+        .map(|size| img.thumbnail(size, size).to_rgba8());
+    (backdrop, palette, icon)
+}
+
+/// Matches a file name against a pattern where "*" matches any run of
+/// characters and "?" any one character, ignoring case.
+pub fn is_name_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    // where to resume after the last "*" if the rest fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p_idx, mut n_idx) = (0, 0);
+    while n_idx < name.len() {
+        match pattern.get(p_idx) {
+            Some('*') => {
+                backtrack = Some((p_idx, n_idx));
+                p_idx += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n_idx] => {
+                p_idx += 1;
+                n_idx += 1;
+            }
+            _ => match backtrack {
+                Some((star_idx, star_n_idx)) => {
+                    p_idx = star_idx + 1;
+                    n_idx = star_n_idx + 1;
+                    backtrack = Some((star_idx, star_n_idx + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p_idx..].iter().all(|c| *c == '*')
+}
+
+/// Finds the file in `dir` matching the earliest of `names`.
+fn find_art_file(dir: &Path, names: &[String]) -> Result<PathBuf, String> {
+    let mut files: Vec<(String, PathBuf)> = std::fs::read_dir(dir)
+        .map_err(|e| format!("failed to read {}: {e}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| !file_type.is_dir()))
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .collect();
+    // so that the same file is picked when several match a pattern
+    files.sort();
+    names
+        .iter()
+        .find_map(|pattern| {
+            files
+                .iter()
+                .find(|(name, _)| is_name_match(pattern, name))
+                .map(|(_, path)| path.clone())
+        })
+        .ok_or_else(|| format!("no file matching \"art-names\" in {}", dir.display()))
+}
+
+fn decode_file(path: &Path, settings: DecodeSettings) -> Result<DecodedArt, String> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let settings = DecodeSettings {
+        format_hint: ImageFormat::from_path(path).ok(),
+        ..settings
+    };
+    decode(&data, &settings)
+}
+
+/// Album art being decoded on a worker thread, so that large images don't
+/// stall the frame loop. Dropping the job discards its result.
+pub struct ArtDecodeJob {
+    receiver: Receiver<Result<DecodedArt, String>>,
+    pub source: ArtSource,
+    /// The booklet page being decoded, if any.
+    pub page: Option<PathBuf>,
+    /// Set for booklet pages and for going back from them to the art of the
+    /// song, which always crossfade.
+    pub is_page_turn: bool,
+    /// Set when the art is decoded again at a different size, replacing the
+    /// current art without a transition.
+    pub is_refresh: bool,
+}
+
+impl ArtDecodeJob {
+    pub fn start(
+        data: Vec<u8>,
+        source: ArtSource,
+        settings: DecodeSettings,
+        is_refresh: bool,
+    ) -> Self {
+        Self::spawn(source, None, is_refresh, move || decode(&data, &settings))
+    }
+
+    /// Looks up the art file in `dir` on the worker thread too, as the music
+    /// directory may be on a slow network share.
+    pub fn start_local(
+        dir: PathBuf,
+        names: Vec<String>,
+        settings: DecodeSettings,
+        is_refresh: bool,
+    ) -> Self {
+        Self::spawn(ArtSource::LocalDir, None, is_refresh, move || {
+            decode_file(&find_art_file(&dir, &names)?, settings)
+        })
+    }
+
+    /// Decodes a page of the booklet slideshow.
+    pub fn start_page(path: PathBuf, settings: DecodeSettings, is_refresh: bool) -> Self {
+        let page = path.clone();
+        // the window icon stays the cover the booklet started with
+        let settings = DecodeSettings {
+            icon_size: None,
+            ..settings
+        };
+        Self::spawn(ArtSource::LocalDir, Some(page), is_refresh, move || {
+            decode_file(&path, settings)
+        })
+    }
+
+    /// Generates the placeholder art for songs without any.
+    pub fn start_placeholder(album: String, artist: String, settings: DecodeSettings) -> Self {
+        Self::spawn(ArtSource::Placeholder, None, false, move || {
+            let img = DynamicImage::ImageRgba8(placeholder::generate(&album, &artist));
+            Ok(prepare_still(img, &settings))
+        })
+    }
+
+    fn spawn(
+        source: ArtSource,
+        page: Option<PathBuf>,
+        is_refresh: bool,
+        job: impl FnOnce() -> Result<DecodedArt, String> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // the receiver is gone if the song changed in the meantime
+            let _ = sender.send(job());
+        });
+        Self {
+            receiver,
+            source,
+            is_page_turn: page.is_some(),
+            page,
+            is_refresh,
+        }
+    }
+
+    /// Returns None while the art is still being decoded.
+    pub fn poll(&self) -> Option<Result<DecodedArt, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err(String::from("decoding thread stopped unexpectedly")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn settings(fit: ArtFit, max_size: Option<u32>) -> DecodeSettings {
+        DecodeSettings {
+            format_hint: None,
+            fit,
+            area: Rect::new(0.0, 0.0, 1920.0, 1080.0),
+            max_size,
+            backdrop: None,
+            extract_palette: false,
+            animation_memory_limit: None,
+            icon_size: None,
+        }
+    }
+
+    #[test]
+    fn test_name_match() {
+        assert!(is_name_match("cover.*", "Cover.JPG"));
+        assert!(is_name_match("albumart*.*", "AlbumArt_{ABC}_Large.jpg"));
+        assert!(is_name_match("front.???", "front.png"));
+        assert!(!is_name_match("front.???", "front.jpeg"));
+        assert!(!is_name_match("cover.*", "cover"));
+        assert!(!is_name_match("folder.*", "subfolder.jpg"));
+        assert!(is_name_match("*", ""));
+    }
+
+    #[test]
+    fn test_downscale_factor() {
+        assert_eq!(
+            downscale_factor(6000, 6000, &settings(ArtFit::Contain, None)),
+            0.18
+        );
+        assert_eq!(
+            downscale_factor(6000, 6000, &settings(ArtFit::Cover, None)),
+            0.32
+        );
+        assert_eq!(
+            downscale_factor(500, 500, &settings(ArtFit::Contain, None)),
+            1.0
+        );
+        assert_eq!(
+            downscale_factor(6000, 6000, &settings(ArtFit::Original, Some(3000))),
+            0.5
+        );
+        assert_eq!(
+            downscale_factor(1000, 1000, &settings(ArtFit::Contain, Some(500))),
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_decode_sniffed_formats() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, Rgba([10, 20, 30, 255])));
+        for format in [
+            ImageFormat::WebP,
+            ImageFormat::Bmp,
+            ImageFormat::Tiff,
+            ImageFormat::Qoi,
+        ] {
+            let mut data = Vec::new();
+            img.write_to(&mut Cursor::new(&mut data), format).unwrap();
+            // a wrong hint from MPD is ignored
+            let settings = DecodeSettings {
+                format_hint: Some(ImageFormat::Png),
+                ..settings(ArtFit::Original, None)
+            };
+            let decoded = decode(&data, &settings).unwrap();
+            assert_eq!(decoded.image.dimensions(), (3, 2), "{format:?}");
+        }
+
+        assert!(detect_format(b"not an image", None).is_err());
+        assert_eq!(
+            detect_format(b"not an image", format_from_mime_type("image/jpg")),
+            Ok(ImageFormat::Jpeg)
+        );
+    }
+
+    #[test]
+    fn test_decode_animation() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut data);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                let buffer = RgbaImage::from_pixel(4, 4, Rgba(color));
+                let frame = image::Frame::from_parts(
+                    buffer,
+                    0,
+                    0,
+                    image::Delay::from_numer_denom_ms(50, 1),
+                );
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+
+        let settings = DecodeSettings {
+            animation_memory_limit: Some(1024),
+            ..settings(ArtFit::Contain, None)
+        };
+        let decoded = decode(&data, &settings).unwrap();
+        assert_eq!(decoded.frames.len(), 2);
+        assert_eq!(decoded.frames[1].1, Duration::from_millis(50));
+
+        // two 4x4 frames take 128 bytes
+        let settings = DecodeSettings {
+            animation_memory_limit: Some(100),
+            ..settings
+        };
+        let decoded = decode(&data, &settings).unwrap();
+        assert!(decoded.frames.is_empty());
+        assert!(decoded.is_animation_too_large);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::album_art;
+use image::ImageFormat;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Name of the subfolder of the album directory that "booklet-scans" adds.
+const SCANS_DIR_NAME: &str = "scans";
+
+/// The images in `dir` that look like they can be decoded, sorted by name.
+fn list_images(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| !file_type.is_dir()))
+        .map(|entry| entry.path())
+        .filter(|path| ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled()))
+        .collect();
+    images.sort();
+    images
+}
+
+/// Collects the pages of the booklet: the images in `dir` other than the
+/// front cover found by `names`, then those in its "scans" subfolder.
+pub fn list_pages(dir: &Path, names: &[String], include_scans: bool) -> Vec<PathBuf> {
+    let mut pages = list_images(dir);
+    let front_idx = names.iter().find_map(|pattern| {
+        pages.iter().position(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| album_art::is_name_match(pattern, name))
+        })
+    });
+    if let Some(front_idx) = front_idx {
+        pages.remove(front_idx);
+    }
+    if include_scans && let Ok(entries) = std::fs::read_dir(dir) {
+        let mut scan_dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(SCANS_DIR_NAME))
+            })
+            .map(|entry| entry.path())
+            .collect();
+        scan_dirs.sort();
+        for scan_dir in scan_dirs {
+            pages.extend(list_images(&scan_dir));
+        }
+    }
+    pages
+}
+
+/// Cycles through the images in the album directory, such as scans of the
+/// booklet and the back cover. Page 0 is the art shown for the song itself,
+/// the others are `pages` in order.
+pub struct Booklet {
+    pub dir: PathBuf,
+    /// Pending until the album directory has been listed on a worker thread.
+    receiver: Option<Receiver<Vec<PathBuf>>>,
+    pages: Vec<PathBuf>,
+    page_idx: usize,
+    elapsed: f32,
+}
+
+impl Booklet {
+    pub fn start(dir: PathBuf, names: Vec<String>, include_scans: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let list_dir = dir.clone();
+        thread::spawn(move || {
+            let _ = sender.send(list_pages(&list_dir, &names, include_scans));
+        });
+        Self {
+            dir,
+            receiver: Some(receiver),
+            pages: Vec::new(),
+            page_idx: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Goes back to the first page, for the next song of the same album.
+    pub fn restart(&mut self) {
+        self.page_idx = 0;
+        self.elapsed = 0.0;
+    }
+
+    /// Returns true once `interval` seconds have passed on the current page
+    /// and the next one should be shown.
+    pub fn advance(&mut self, delta: f32, interval: f32) -> bool {
+        if let Some(receiver) = &self.receiver
+            && let Ok(pages) = receiver.try_recv()
+        {
+            self.pages = pages;
+            self.receiver = None;
+        }
+        if self.pages.is_empty() {
+            return false;
+        }
+        self.elapsed += delta;
+        if self.elapsed < interval {
+            return false;
+        }
+        self.elapsed = 0.0;
+        self.page_idx = (self.page_idx + 1) % (self.pages.len() + 1);
+        true
+    }
+
+    /// The page being shown instead of the art of the song, if any.
+    pub fn current_page(&self) -> Option<&Path> {
+        self.page_idx
+            .checked_sub(1)
+            .map(|idx| self.pages[idx].as_path())
+    }
+
+    /// Drops a page that failed to load, so that it is skipped from now on.
+    pub fn remove_page(&mut self, page: &Path) {
+        if let Some(idx) = self.pages.iter().position(|path| path == page) {
+            self.pages.remove(idx);
+            if idx < self.page_idx {
+                self.page_idx -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_pages() {
+        let dir = std::env::temp_dir().join(format!("booklet_test_{}", std::process::id()));
+        let scans = dir.join("Scans");
+        std::fs::create_dir_all(&scans).unwrap();
+        for path in [
+            dir.join("back.jpg"),
+            dir.join("Folder.jpg"),
+            dir.join("01 - song.flac"),
+            dir.join("inlay.png"),
+            scans.join("page2.jpg"),
+            scans.join("page1.jpg"),
+        ] {
+            std::fs::write(path, []).unwrap();
+        }
+        let names = vec![String::from("cover.*"), String::from("folder.*")];
+
+        assert_eq!(
+            list_pages(&dir, &names, false),
+            [dir.join("back.jpg"), dir.join("inlay.png")]
+        );
+        assert_eq!(
+            list_pages(&dir, &names, true)[2..],
+            [scans.join("page1.jpg"), scans.join("page2.jpg")]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut booklet = Booklet {
+            dir: PathBuf::new(),
+            receiver: None,
+            pages: vec![PathBuf::from("back.jpg"), PathBuf::from("inlay.png")],
+            page_idx: 0,
+            elapsed: 0.0,
+        };
+        assert!(!booklet.advance(5.0, 10.0));
+        assert_eq!(booklet.current_page(), None);
+        assert!(booklet.advance(5.0, 10.0));
+        assert_eq!(booklet.current_page(), Some(Path::new("back.jpg")));
+        booklet.remove_page(Path::new("back.jpg"));
+        assert_eq!(booklet.current_page(), None);
+        assert!(booklet.advance(10.0, 10.0));
+        assert_eq!(booklet.current_page(), Some(Path::new("inlay.png")));
+        // back to the art of the song after the last page
+        assert!(booklet.advance(10.0, 10.0));
+        assert_eq!(booklet.current_page(), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::f32::consts::TAU;
+
+/// The positions the pixel shift steps through, as multiples of the
+/// distance, circling the origin so that no pixel stays lit in one place.
+const SHIFT_PATH: [(f32, f32); 9] = [
+    (0.0, 0.0),
+    (1.0, 0.0),
+    (1.0, 1.0),
+    (0.0, 1.0),
+    (-1.0, 1.0),
+    (-1.0, 0.0),
+    (-1.0, -1.0),
+    (0.0, -1.0),
+    (1.0, -1.0),
+];
+/// Seconds the text drift takes to go back and forth horizontally and
+/// vertically. They differ, so that the text traces a slow Lissajous curve.
+const DRIFT_PERIODS: (f32, f32) = (600.0, 420.0);
+/// Seconds the dimming takes to fade in.
+pub const DIM_FADE_TIME: f32 = 2.0;
+
+/// Offset of everything drawn, moving to the next position of the path
+/// every `interval` seconds.
+pub fn pixel_shift_offset(elapsed: f32, distance: u32, interval: f32) -> [f32; 2] {
+    if distance == 0 {
+        return [0.0, 0.0];
+    }
+    let step = (elapsed / interval) as usize % SHIFT_PATH.len();
+    let (x, y) = SHIFT_PATH[step];
+    [x * distance as f32, y * distance as f32]
+}
+
+/// Offset of the text, slowly drifting up to `distance` pixels away from
+/// where it is laid out.
+pub fn drift_offset(elapsed: f32, distance: u32) -> [f32; 2] {
+    if distance == 0 {
+        return [0.0, 0.0];
+    }
+    let distance = distance as f32;
+    [
+        (elapsed / DRIFT_PERIODS.0 * TAU).sin() * distance,
+        (elapsed / DRIFT_PERIODS.1 * TAU).sin() * distance,
+    ]
+}
+
+/// How opaque the black overlay dimming the window is, `idle_time` seconds
+/// after playback stopped or paused.
+pub fn dim_alpha(idle_time: f32, dim_after: Option<f32>, brightness: u8) -> f32 {
+    let Some(dim_after) = dim_after else {
+        return 0.0;
+    };
+    let progress = ((idle_time - dim_after) / DIM_FADE_TIME).clamp(0.0, 1.0);
+    progress * (1.0 - brightness as f32 / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(pixel_shift_offset(10.0, 3, 60.0), [0.0, 0.0]);
+        assert_eq!(pixel_shift_offset(130.0, 3, 60.0), [3.0, 3.0]);
+        assert_eq!(pixel_shift_offset(9.0 * 60.0, 3, 60.0), [0.0, 0.0]);
+        assert_eq!(drift_offset(1234.0, 0), [0.0, 0.0]);
+        let [x, y] = drift_offset(150.0, 20);
+        assert!((x - 20.0).abs() < 0.001 && y > 0.0 && y < 20.0);
+
+        assert_eq!(dim_alpha(500.0, None, 30), 0.0);
+        assert_eq!(dim_alpha(59.0, Some(60.0), 30), 0.0);
+        assert!((dim_alpha(61.0, Some(60.0), 30) - 0.35).abs() < 0.001);
+        assert!((dim_alpha(100.0, Some(60.0), 30) - 0.7).abs() < 0.001);
+    }
+}
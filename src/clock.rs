@@ -0,0 +1,187 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i64,
+    /// 1 to 12.
+    pub month: u32,
+    /// 1 to 31.
+    pub day: u32,
+    /// 0 is Sunday.
+    pub weekday: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl LocalTime {
+    pub fn now() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        local_time(secs).unwrap_or_else(|| utc_time(secs))
+    }
+
+    /// Formats the time with a subset of the strftime conversions: %H, %I,
+    /// %M, %S, %p, %Y, %m, %d, %e, %a, %A, %b, %B and %%.
+    pub fn format(&self, format: &str) -> String {
+        let mut formatted = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+            let weekday = WEEKDAYS[self.weekday as usize % 7];
+            let month = MONTHS[(self.month as usize + 11) % 12];
+            match chars.next() {
+                Some('H') => formatted.push_str(&format!("{:02}", self.hour)),
+                Some('I') => formatted.push_str(&format!("{:02}", (self.hour + 11) % 12 + 1)),
+                Some('M') => formatted.push_str(&format!("{:02}", self.minute)),
+                Some('S') => formatted.push_str(&format!("{:02}", self.second)),
+                Some('p') => formatted.push_str(if self.hour < 12 { "AM" } else { "PM" }),
+                Some('Y') => formatted.push_str(&self.year.to_string()),
+                Some('m') => formatted.push_str(&format!("{:02}", self.month)),
+                Some('d') => formatted.push_str(&format!("{:02}", self.day)),
+                Some('e') => formatted.push_str(&self.day.to_string()),
+                Some('a') => formatted.push_str(&weekday[..3]),
+                Some('A') => formatted.push_str(weekday),
+                Some('b') => formatted.push_str(&month[..3]),
+                Some('B') => formatted.push_str(month),
+                Some('%') => formatted.push('%'),
+                // unknown conversions are kept as is
+                Some(other) => {
+                    formatted.push('%');
+                    formatted.push(other);
+                }
+                None => formatted.push('%'),
+            }
+        }
+        formatted
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn local_time(secs: i64) -> Option<LocalTime> {
+    let time = secs as libc::time_t;
+    let mut tm: std::mem::MaybeUninit<libc::tm> = std::mem::MaybeUninit::zeroed();
+    let tm = unsafe {
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return None;
+        }
+        tm.assume_init()
+    };
+    Some(LocalTime {
+        year: tm.tm_year as i64 + 1900,
+        month: tm.tm_mon as u32 + 1,
+        day: tm.tm_mday as u32,
+        weekday: tm.tm_wday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        second: tm.tm_sec as u32,
+    })
+}
+
+/// The time zone isn't looked up on other platforms, so the clock shows UTC.
+#[cfg(not(target_family = "unix"))]
+fn local_time(_secs: i64) -> Option<LocalTime> {
+    None
+}
+
+/// Converts seconds since the epoch to a date in the proleptic Gregorian
+/// calendar, see http://howardhinnant.github.io/date_algorithms.html
+fn utc_time(secs: i64) -> LocalTime {
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400) as u32;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    LocalTime {
+        year,
+        month,
+        day,
+        // the epoch was a Thursday
+        weekday: (days + 4).rem_euclid(7) as u32,
+        hour: secs_of_day / 3600,
+        minute: secs_of_day / 60 % 60,
+        second: secs_of_day % 60,
+    }
+}
+
+/// Formats an uptime in seconds like "3d 4h", "4h 5m" or "5m".
+pub fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_time() {
+        let time = utc_time(951782400 + 13 * 3600 + 5 * 60 + 9);
+        assert_eq!(
+            time,
+            LocalTime {
+                year: 2000,
+                month: 2,
+                day: 29,
+                weekday: 2,
+                hour: 13,
+                minute: 5,
+                second: 9,
+            }
+        );
+        assert_eq!(
+            time.format("%a %e %b %Y, %I:%M:%S %p (%H%%)"),
+            "Tue 29 Feb 2000, 01:05:09 PM (13%)"
+        );
+        assert_eq!(
+            utc_time(0).format("%A, %B %d %q"),
+            "Thursday, January 01 %q"
+        );
+        assert_eq!(format_uptime(3 * 86400 + 4 * 3600 + 59), "3d 4h");
+    }
+}
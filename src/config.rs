@@ -0,0 +1,1260 @@
+use crate::Opt;
+use crate::debug_log::{LogLevel, log_warning};
+use crate::template::Template;
+use crate::transition::TransitionKind;
+use crate::window::Geometry;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, FromArgMatches, ValueEnum};
+use ggez::graphics::Color;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+const CONFIG_DIR_NAME: &str = "mpd_info_screen";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+pub const DEFAULT_MARGIN: f32 = 0.3;
+pub const DEFAULT_ROW_SPACING: f32 = 0.4;
+pub const DEFAULT_TIMER_HEIGHT_RATIO: f32 = 0.875;
+pub const DEFAULT_TEXT_HEIGHT_RATIO: f32 = 0.12;
+pub const DEFAULT_SPLIT_ASPECT_RATIO: f32 = 1.6;
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextRow {
+    Title,
+    Artist,
+    Album,
+    Filename,
+    Timer,
+}
+
+#[derive(Debug, Clone)]
+pub struct RowTheme {
+    pub text_color: Color,
+    pub text_bg_color: Color,
+    pub text_bg_padding: f32,
+    pub font: Option<PathBuf>,
+}
+
+impl Default for RowTheme {
+    fn default() -> Self {
+        Self {
+            text_color: Color::WHITE,
+            text_bg_color: Color::from_rgba(0, 0, 0, 190),
+            text_bg_padding: 0.0,
+            font: None,
+        }
+    }
+}
+
+/// Colors and other visual settings that are only set from the config file.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub background_color: Color,
+    /// Used for everything that is not one of the text rows.
+    pub default: RowTheme,
+    pub title: RowTheme,
+    pub artist: RowTheme,
+    pub album: RowTheme,
+    pub filename: RowTheme,
+    pub timer: RowTheme,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background_color: Color::BLACK,
+            default: RowTheme::default(),
+            title: RowTheme::default(),
+            artist: RowTheme::default(),
+            album: RowTheme::default(),
+            filename: RowTheme::default(),
+            timer: RowTheme::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn row(&self, row: TextRow) -> &RowTheme {
+        match row {
+            TextRow::Title => &self.title,
+            TextRow::Artist => &self.artist,
+            TextRow::Album => &self.album,
+            TextRow::Filename => &self.filename,
+            TextRow::Timer => &self.timer,
+        }
+    }
+}
+
+/// What each of the song text rows shows.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    pub title: Template,
+    pub artist: Template,
+    pub album: Template,
+    pub filename: Template,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            title: Template::field("title"),
+            artist: Template::field("artist"),
+            album: Template::field("album"),
+            filename: Template::field("filename"),
+        }
+    }
+}
+
+impl Templates {
+    /// There is no template for the timer row.
+    pub fn row(&self, row: TextRow) -> Option<&Template> {
+        match row {
+            TextRow::Title => Some(&self.title),
+            TextRow::Artist => Some(&self.artist),
+            TextRow::Album => Some(&self.album),
+            TextRow::Filename => Some(&self.filename),
+            TextRow::Timer => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct TemplateValues {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    filename: Option<String>,
+}
+
+impl TemplateValues {
+    fn merge(&mut self, other: &TemplateValues) {
+        for (value, other) in [
+            (&mut self.title, &other.title),
+            (&mut self.artist, &other.artist),
+            (&mut self.album, &other.album),
+            (&mut self.filename, &other.filename),
+        ] {
+            if other.is_some() {
+                value.clone_from(other);
+            }
+        }
+    }
+
+    fn resolve(&self) -> Result<Templates, String> {
+        let parse = |value: &Option<String>, name: &str| match value {
+            Some(template) => Template::parse(template)
+                .map_err(|e| format!("Invalid template for the {name} row: {e}")),
+            None => Ok(Template::field(name)),
+        };
+        Ok(Templates {
+            title: parse(&self.title, "title")?,
+            artist: parse(&self.artist, "artist")?,
+            album: parse(&self.album, "album")?,
+            filename: parse(&self.filename, "filename")?,
+        })
+    }
+}
+
+/// What to do with text rows that are too wide for the window.
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextOverflow {
+    Shrink,
+    Marquee,
+}
+
+/// How the album art is scaled to the window.
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtFit {
+    Contain,
+    Cover,
+    Stretch,
+    Original,
+    Integer,
+}
+
+/// Which part of the album art stays in view when it is cropped.
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtAnchor {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ArtAnchor {
+    /// Returns how much of the space left over (or cropped) goes before the
+    /// art, horizontally and vertically.
+    pub fn fractions(self) -> (f32, f32) {
+        match self {
+            ArtAnchor::Center => (0.5, 0.5),
+            ArtAnchor::Top => (0.5, 0.0),
+            ArtAnchor::Bottom => (0.5, 1.0),
+            ArtAnchor::Left => (0.0, 0.5),
+            ArtAnchor::Right => (1.0, 0.5),
+            ArtAnchor::TopLeft => (0.0, 0.0),
+            ArtAnchor::TopRight => (1.0, 0.0),
+            ArtAnchor::BottomLeft => (0.0, 1.0),
+            ArtAnchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// Where MPD listens: an IPv4 address, used with "port", or the path of its
+/// local socket.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Host {
+    Ip(Ipv4Addr),
+    Socket(PathBuf),
+}
+
+impl FromStr for Host {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('/') {
+            return Ok(Host::Socket(PathBuf::from(s)));
+        }
+        s.parse()
+            .map(Host::Ip)
+            .map_err(|_| format!("invalid host \"{s}\", expected an IPv4 address or a socket path"))
+    }
+}
+
+impl TryFrom<String> for Host {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl std::fmt::Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Host::Ip(addr) => write!(f, "{addr}"),
+            Host::Socket(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// How far the whole picture is turned clockwise, for displays mounted on
+/// their side or upside down.
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(try_from = "u16")]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    None,
+    #[value(name = "90")]
+    Clockwise,
+    #[value(name = "180")]
+    UpsideDown,
+    #[value(name = "270")]
+    CounterClockwise,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Clockwise),
+            180 => Ok(Rotation::UpsideDown),
+            270 => Ok(Rotation::CounterClockwise),
+            _ => Err(format!("rotation must be 0, 90, 180 or 270, not {degrees}")),
+        }
+    }
+}
+
+impl Rotation {
+    pub fn angle(self) -> f32 {
+        match self {
+            Rotation::None => 0.0,
+            Rotation::Clockwise => std::f32::consts::FRAC_PI_2,
+            Rotation::UpsideDown => std::f32::consts::PI,
+            Rotation::CounterClockwise => -std::f32::consts::FRAC_PI_2,
+        }
+    }
+
+    /// The size of the picture before it is turned, given the window size.
+    pub fn logical_size(self, width: f32, height: f32) -> (f32, f32) {
+        match self {
+            Rotation::None | Rotation::UpsideDown => (width, height),
+            Rotation::Clockwise | Rotation::CounterClockwise => (height, width),
+        }
+    }
+
+    /// Maps a point in the window to the picture before it is turned.
+    pub fn to_logical(self, x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+        match self {
+            Rotation::None => (x, y),
+            Rotation::Clockwise => (y, width - x),
+            Rotation::UpsideDown => (width - x, height - y),
+            Rotation::CounterClockwise => (height - y, x),
+        }
+    }
+}
+
+/// Where album art is looked for, tried in the order given by "art-sources".
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtSource {
+    Embedded,
+    MpdFolder,
+    LocalDir,
+    Placeholder,
+}
+
+impl ArtSource {
+    pub fn description(self) -> &'static str {
+        match self {
+            ArtSource::Embedded => "embedded album art",
+            ArtSource::MpdFolder => "album art file in the song's directory",
+            ArtSource::LocalDir => "album art file in the music directory",
+            ArtSource::Placeholder => "placeholder art",
+        }
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Where the block of text rows is placed vertically.
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerticalAnchor {
+    Top,
+    Middle,
+    #[default]
+    Bottom,
+}
+
+/// When to show the album art and the text rows side by side.
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SplitMode {
+    #[default]
+    Never,
+    /// Once the window is wider than "split-aspect-ratio".
+    Auto,
+    Always,
+}
+
+/// The side of the window the album art is placed on when split.
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SplitSide {
+    #[default]
+    Left,
+    Right,
+}
+
+/// Placement settings for the text rows that are only set from the config file.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub margin: f32,
+    pub vertical_margin: f32,
+    pub row_spacing: f32,
+    pub timer_height_ratio: f32,
+    /// The rows to show, from top to bottom.
+    pub order: Vec<TextRow>,
+    pub align: HorizontalAlign,
+    pub anchor: VerticalAnchor,
+    /// Row heights relative to the window height, overriding the defaults.
+    pub heights: BTreeMap<TextRow, f32>,
+    pub split: SplitMode,
+    pub split_side: SplitSide,
+    /// Width divided by height above which "auto" splits the window.
+    pub split_aspect_ratio: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            margin: DEFAULT_MARGIN,
+            vertical_margin: 0.0,
+            row_spacing: DEFAULT_ROW_SPACING,
+            timer_height_ratio: DEFAULT_TIMER_HEIGHT_RATIO,
+            order: vec![
+                TextRow::Timer,
+                TextRow::Title,
+                TextRow::Artist,
+                TextRow::Album,
+                TextRow::Filename,
+            ],
+            align: HorizontalAlign::default(),
+            anchor: VerticalAnchor::default(),
+            heights: BTreeMap::new(),
+            split: SplitMode::default(),
+            split_side: SplitSide::default(),
+            split_aspect_ratio: DEFAULT_SPLIT_ASPECT_RATIO,
+        }
+    }
+}
+
+impl Layout {
+    /// "force-text-height-scale" takes precedence over the heights from the
+    /// config file.
+    pub fn height_ratio(&self, row: TextRow, forced_scale: Option<f32>) -> f32 {
+        if let Some(forced_scale) = forced_scale {
+            if row == TextRow::Timer {
+                forced_scale * self.timer_height_ratio
+            } else {
+                forced_scale
+            }
+        } else if let Some(height) = self.heights.get(&row) {
+            *height
+        } else if row == TextRow::Timer {
+            DEFAULT_TEXT_HEIGHT_RATIO * self.timer_height_ratio
+        } else {
+            DEFAULT_TEXT_HEIGHT_RATIO
+        }
+    }
+
+    /// Returns the side the album art goes on if a window of the given size
+    /// is split.
+    pub fn split_side_for(&self, width: f32, height: f32) -> Option<SplitSide> {
+        let is_split = match self.split {
+            SplitMode::Never => false,
+            SplitMode::Always => true,
+            SplitMode::Auto => height > 0.0 && width / height >= self.split_aspect_ratio,
+        };
+        is_split.then_some(self.split_side)
+    }
+}
+
+/// A "#rrggbb" or "#rrggbbaa" color from the config file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ColorValue {
+    color: Color,
+    has_alpha: bool,
+}
+
+impl ColorValue {
+    /// Colors without an explicit alpha use the given opacity instead.
+    fn with_default_alpha(self, alpha: u8) -> Color {
+        if self.has_alpha {
+            self.color
+        } else {
+            let (r, g, b) = self.color.to_rgb();
+            Color::from_rgba(r, g, b, alpha)
+        }
+    }
+}
+
+fn parse_color(string: &str) -> Result<ColorValue, String> {
+    let hex = string
+        .strip_prefix('#')
+        .ok_or_else(|| format!("color \"{string}\" must start with '#'"))?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "color \"{string}\" must be in the form \"#rrggbb\" or \"#rrggbbaa\""
+        ));
+    }
+    let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap();
+    let has_alpha = hex.len() == 8;
+    let alpha = if has_alpha { channel(6) } else { 255 };
+    Ok(ColorValue {
+        color: Color::from_rgba(channel(0), channel(2), channel(4), alpha),
+        has_alpha,
+    })
+}
+
+impl<'de> Deserialize<'de> for ColorValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        parse_color(&string).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RowThemeValues {
+    text_color: Option<ColorValue>,
+    text_bg_color: Option<ColorValue>,
+    text_bg_padding: Option<f32>,
+    font: Option<PathBuf>,
+}
+
+impl RowThemeValues {
+    fn merge(&mut self, other: &RowThemeValues) {
+        self.text_color = other.text_color.or(self.text_color);
+        self.text_bg_color = other.text_bg_color.or(self.text_bg_color);
+        self.text_bg_padding = other.text_bg_padding.or(self.text_bg_padding);
+        if other.font.is_some() {
+            self.font.clone_from(&other.font);
+        }
+    }
+
+    fn resolve(&self, text_bg_opacity: u8, config_dir: Option<&Path>) -> RowTheme {
+        RowTheme {
+            text_color: self
+                .text_color
+                .map_or(Color::WHITE, |c| c.with_default_alpha(255)),
+            text_bg_color: self
+                .text_bg_color
+                .map_or(Color::from_rgba(0, 0, 0, text_bg_opacity), |c| {
+                    c.with_default_alpha(text_bg_opacity)
+                }),
+            text_bg_padding: self.text_bg_padding.unwrap_or(0.0).max(0.0),
+            // relative font paths are relative to the config file
+            font: self.font.as_ref().map(|font| match config_dir {
+                Some(dir) if font.is_relative() => dir.join(font),
+                _ => font.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ThemeValues {
+    background_color: Option<ColorValue>,
+    text_color: Option<ColorValue>,
+    text_bg_color: Option<ColorValue>,
+    text_bg_padding: Option<f32>,
+    font: Option<PathBuf>,
+    title: Option<RowThemeValues>,
+    artist: Option<RowThemeValues>,
+    album: Option<RowThemeValues>,
+    filename: Option<RowThemeValues>,
+    timer: Option<RowThemeValues>,
+}
+
+impl ThemeValues {
+    fn defaults(&self) -> RowThemeValues {
+        RowThemeValues {
+            text_color: self.text_color,
+            text_bg_color: self.text_bg_color,
+            text_bg_padding: self.text_bg_padding,
+            font: self.font.clone(),
+        }
+    }
+
+    fn merge(&mut self, other: &ThemeValues) {
+        fn merge_row(row: &mut Option<RowThemeValues>, other: &Option<RowThemeValues>) {
+            if let Some(other) = other {
+                row.get_or_insert_with(RowThemeValues::default).merge(other);
+            }
+        }
+
+        self.background_color = other.background_color.or(self.background_color);
+        self.text_color = other.text_color.or(self.text_color);
+        self.text_bg_color = other.text_bg_color.or(self.text_bg_color);
+        self.text_bg_padding = other.text_bg_padding.or(self.text_bg_padding);
+        if other.font.is_some() {
+            self.font.clone_from(&other.font);
+        }
+        merge_row(&mut self.title, &other.title);
+        merge_row(&mut self.artist, &other.artist);
+        merge_row(&mut self.album, &other.album);
+        merge_row(&mut self.filename, &other.filename);
+        merge_row(&mut self.timer, &other.timer);
+    }
+
+    fn resolve(&self, text_bg_opacity: u8, config_dir: Option<&Path>) -> Theme {
+        let defaults = self.defaults();
+        let resolve_row = |row: &Option<RowThemeValues>| {
+            let mut values = defaults.clone();
+            if let Some(row) = row {
+                values.merge(row);
+            }
+            values.resolve(text_bg_opacity, config_dir)
+        };
+        Theme {
+            background_color: self
+                .background_color
+                .map_or(Color::BLACK, |c| c.with_default_alpha(255)),
+            default: defaults.resolve(text_bg_opacity, config_dir),
+            title: resolve_row(&self.title),
+            artist: resolve_row(&self.artist),
+            album: resolve_row(&self.album),
+            filename: resolve_row(&self.filename),
+            timer: resolve_row(&self.timer),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct LayoutValues {
+    margin: Option<f32>,
+    vertical_margin: Option<f32>,
+    row_spacing: Option<f32>,
+    timer_height_ratio: Option<f32>,
+    order: Option<Vec<TextRow>>,
+    align: Option<HorizontalAlign>,
+    anchor: Option<VerticalAnchor>,
+    heights: Option<BTreeMap<TextRow, f32>>,
+    split: Option<SplitMode>,
+    split_side: Option<SplitSide>,
+    split_aspect_ratio: Option<f32>,
+}
+
+impl LayoutValues {
+    fn merge(&mut self, other: &LayoutValues) {
+        self.margin = other.margin.or(self.margin);
+        self.vertical_margin = other.vertical_margin.or(self.vertical_margin);
+        self.row_spacing = other.row_spacing.or(self.row_spacing);
+        self.timer_height_ratio = other.timer_height_ratio.or(self.timer_height_ratio);
+        if other.order.is_some() {
+            self.order.clone_from(&other.order);
+        }
+        self.align = other.align.or(self.align);
+        self.anchor = other.anchor.or(self.anchor);
+        self.split = other.split.or(self.split);
+        self.split_side = other.split_side.or(self.split_side);
+        self.split_aspect_ratio = other.split_aspect_ratio.or(self.split_aspect_ratio);
+        if let Some(heights) = &other.heights {
+            self.heights
+                .get_or_insert_with(BTreeMap::new)
+                .extend(heights.iter().map(|(row, height)| (*row, *height)));
+        }
+    }
+
+    fn resolve(&self) -> Result<Layout, String> {
+        let defaults = Layout::default();
+        let order = self.order.clone().unwrap_or(defaults.order);
+        for (idx, row) in order.iter().enumerate() {
+            if order[..idx].contains(row) {
+                return Err(format!("layout order lists {row:?} more than once"));
+            }
+        }
+        if let Some(ratio) = self.split_aspect_ratio
+            && ratio <= 0.0
+        {
+            return Err(format!(
+                "layout split-aspect-ratio must be positive, got {ratio}"
+            ));
+        }
+        Ok(Layout {
+            margin: self.margin.unwrap_or(defaults.margin),
+            vertical_margin: self.vertical_margin.unwrap_or(defaults.vertical_margin),
+            row_spacing: self.row_spacing.unwrap_or(defaults.row_spacing),
+            timer_height_ratio: self
+                .timer_height_ratio
+                .unwrap_or(defaults.timer_height_ratio),
+            order,
+            align: self.align.unwrap_or(defaults.align),
+            anchor: self.anchor.unwrap_or(defaults.anchor),
+            heights: self.heights.clone().unwrap_or(defaults.heights),
+            split: self.split.unwrap_or(defaults.split),
+            split_side: self.split_side.unwrap_or(defaults.split_side),
+            split_aspect_ratio: self
+                .split_aspect_ratio
+                .unwrap_or(defaults.split_aspect_ratio),
+        })
+    }
+}
+
+/// Mirrors the fields of `Opt`, using the same names as the long CLI flags.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigValues {
+    host: Option<Host>,
+    port: Option<u16>,
+    password: Option<String>,
+    disable_show_title: Option<bool>,
+    disable_show_artist: Option<bool>,
+    disable_show_album: Option<bool>,
+    disable_show_filename: Option<bool>,
+    disable_show_percentage: Option<bool>,
+    force_text_height_scale: Option<f32>,
+    #[serde(rename = "pprompt")]
+    enable_prompt_password: Option<bool>,
+    #[serde(rename = "pfile")]
+    password_file: Option<PathBuf>,
+    #[serde(rename = "no-scale-fill")]
+    do_not_fill_scale_album_art: Option<bool>,
+    art_fit: Option<ArtFit>,
+    art_anchor: Option<ArtAnchor>,
+    art_max_size: Option<u32>,
+    disable_art_animation: Option<bool>,
+    art_animation_memory: Option<u32>,
+    disable_placeholder_art: Option<bool>,
+    art_sources: Option<Vec<ArtSource>>,
+    music_dir: Option<PathBuf>,
+    art_names: Option<Vec<String>>,
+    booklet_slideshow: Option<bool>,
+    booklet_interval: Option<f32>,
+    booklet_scans: Option<bool>,
+    art_backdrop: Option<bool>,
+    art_backdrop_blur: Option<f32>,
+    art_backdrop_brightness: Option<u8>,
+    adaptive_theme: Option<bool>,
+    transition: Option<TransitionKind>,
+    transition_time: Option<f32>,
+    text_overflow: Option<TextOverflow>,
+    marquee_speed: Option<f32>,
+    marquee_pause: Option<f32>,
+    wrap_lines: Option<u32>,
+    idle_screen: Option<bool>,
+    idle_after_pause: Option<f32>,
+    idle_stats: Option<bool>,
+    clock_format: Option<String>,
+    date_format: Option<String>,
+    pixel_shift: Option<u32>,
+    pixel_shift_interval: Option<f32>,
+    text_drift: Option<u32>,
+    dim_after: Option<f32>,
+    dim_brightness: Option<u8>,
+    fullscreen: Option<bool>,
+    rotate: Option<Rotation>,
+    borderless: Option<bool>,
+    geometry: Option<Geometry>,
+    monitor: Option<usize>,
+    always_on_top: Option<bool>,
+    window_title: Option<Template>,
+    log_level: Option<LogLevel>,
+    text_bg_opacity: Option<u8>,
+    disable_mouse_control: Option<bool>,
+    show_progress_bar: Option<bool>,
+    disable_touch_gestures: Option<bool>,
+    touch_swipe_distance: Option<f32>,
+    touch_tap_distance: Option<f32>,
+    touch_long_press_ms: Option<u64>,
+    theme: Option<ThemeValues>,
+    layout: Option<LayoutValues>,
+    templates: Option<TemplateValues>,
+    profiles: Option<BTreeMap<String, ConfigValues>>,
+}
+
+fn parse_config(content: &str) -> Result<ConfigValues, String> {
+    let values: ConfigValues = toml::from_str(content).map_err(|e| e.to_string())?;
+    if let Some(profiles) = &values.profiles {
+        for (name, profile) in profiles {
+            if profile.profiles.is_some() {
+                return Err(format!("profile \"{name}\" must not contain more profiles"));
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn is_set_on_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Theme, layout and template values are collected separately, as they are
+/// resolved only after every source has been applied.
+#[derive(Default)]
+struct PendingStyle {
+    theme: ThemeValues,
+    layout: LayoutValues,
+    templates: TemplateValues,
+}
+
+impl ConfigValues {
+    fn apply(&self, opt: &mut Opt, style: &mut PendingStyle, matches: &ArgMatches) {
+        macro_rules! apply_value {
+            ($($field:ident),*) => {
+                $(
+                    if let Some(value) = &self.$field
+                        && !is_set_on_command_line(matches, stringify!($field))
+                    {
+                        opt.$field = value.clone();
+                    }
+                )*
+            };
+        }
+        macro_rules! apply_option {
+            ($($field:ident),*) => {
+                $(
+                    if let Some(value) = &self.$field
+                        && !is_set_on_command_line(matches, stringify!($field))
+                    {
+                        opt.$field = Some(value.clone());
+                    }
+                )*
+            };
+        }
+
+        apply_option!(
+            host,
+            password,
+            force_text_height_scale,
+            password_file,
+            art_max_size,
+            music_dir,
+            idle_after_pause,
+            dim_after,
+            geometry,
+            monitor
+        );
+        apply_value!(
+            port,
+            disable_show_title,
+            disable_show_artist,
+            disable_show_album,
+            disable_show_filename,
+            disable_show_percentage,
+            enable_prompt_password,
+            do_not_fill_scale_album_art,
+            art_fit,
+            art_anchor,
+            disable_art_animation,
+            art_animation_memory,
+            disable_placeholder_art,
+            art_sources,
+            art_names,
+            booklet_slideshow,
+            booklet_interval,
+            booklet_scans,
+            art_backdrop,
+            art_backdrop_blur,
+            art_backdrop_brightness,
+            adaptive_theme,
+            transition,
+            transition_time,
+            text_overflow,
+            marquee_speed,
+            marquee_pause,
+            wrap_lines,
+            idle_screen,
+            idle_stats,
+            clock_format,
+            date_format,
+            pixel_shift,
+            pixel_shift_interval,
+            text_drift,
+            dim_brightness,
+            fullscreen,
+            rotate,
+            borderless,
+            always_on_top,
+            window_title,
+            log_level,
+            text_bg_opacity,
+            disable_mouse_control,
+            show_progress_bar,
+            disable_touch_gestures,
+            touch_swipe_distance,
+            touch_tap_distance,
+            touch_long_press_ms
+        );
+
+        if let Some(theme) = &self.theme {
+            style.theme.merge(theme);
+        }
+        if let Some(layout) = &self.layout {
+            style.layout.merge(layout);
+        }
+        if let Some(templates) = &self.templates {
+            style.templates.merge(templates);
+        }
+    }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Builds the `Opt` from the command line and the config file, where values
+/// given on the command line take precedence over the config file.
+pub struct ConfigSource {
+    matches: ArgMatches,
+    path: Option<PathBuf>,
+    is_path_explicit: bool,
+    profile: Option<String>,
+}
+
+impl ConfigSource {
+    pub fn new(matches: ArgMatches) -> Result<Self, String> {
+        let cli_opt = Opt::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+        let is_path_explicit = cli_opt.config.is_some();
+        let path = cli_opt
+            .config
+            .or_else(default_config_path)
+            .map(|path| std::path::absolute(&path).unwrap_or(path));
+        Ok(Self {
+            matches,
+            path,
+            is_path_explicit,
+            profile: cli_opt.profile,
+        })
+    }
+
+    /// Used to notice when the config file was edited, created or removed.
+    pub fn modified_time(&self) -> Option<SystemTime> {
+        self.path
+            .as_ref()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+
+    fn read_config(&self) -> Result<Option<ConfigValues>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        if !path.exists() && !self.is_path_explicit {
+            return Ok(None);
+        }
+        let mut content = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|e| format!("Failed to read config file {}: {e}", path.display()))?;
+        parse_config(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid config file {}: {e}", path.display()))
+    }
+
+    pub fn load(&self) -> Result<Opt, String> {
+        let mut opt = Opt::from_arg_matches(&self.matches).map_err(|e| e.to_string())?;
+        let mut style = PendingStyle::default();
+
+        let config = self.read_config()?;
+        if let Some(config) = &config {
+            config.apply(&mut opt, &mut style, &self.matches);
+        }
+        if let Some(profile_name) = &self.profile {
+            let profile = config
+                .as_ref()
+                .and_then(|config| config.profiles.as_ref())
+                .and_then(|profiles| profiles.get(profile_name))
+                .ok_or_else(|| format!("Profile \"{profile_name}\" not found in config file"))?;
+            profile.apply(&mut opt, &mut style, &self.matches);
+        }
+
+        if opt.host.is_none() {
+            return Err(String::from(
+                "HOST must be given on the command line or in the config file",
+            ));
+        }
+
+        if let Some(forced_scale) = &mut opt.force_text_height_scale {
+            if *forced_scale < 0.01 {
+                *forced_scale = 0.01;
+                log_warning("Clamped \"force-text-height-scale\" to minimum of 0.01!");
+            } else if *forced_scale > 0.5 {
+                *forced_scale = 0.5;
+                log_warning("Clamped \"force-text-height-scale\" to maximum of 0.5!");
+            }
+        }
+
+        if opt.do_not_fill_scale_album_art && opt.art_fit == ArtFit::Contain {
+            // "no-scale-fill" predates "art-fit"
+            opt.art_fit = ArtFit::Original;
+        }
+
+        for (idx, source) in opt.art_sources.iter().enumerate() {
+            if opt.art_sources[..idx].contains(source) {
+                return Err(format!(
+                    "\"art-sources\" lists \"{}\" more than once",
+                    source
+                        .to_possible_value()
+                        .map_or_else(String::new, |v| v.get_name().into())
+                ));
+            }
+        }
+        if opt.disable_placeholder_art {
+            opt.art_sources
+                .retain(|source| *source != ArtSource::Placeholder);
+        }
+
+        if opt.booklet_slideshow && opt.music_dir.is_none() {
+            log_warning("\"booklet-slideshow\" needs \"music-dir\" to be set!");
+        }
+        if opt.booklet_interval < 1.0 {
+            opt.booklet_interval = 1.0;
+            log_warning("Clamped \"booklet-interval\" to minimum of 1!");
+        }
+
+        if let Some(idle_after_pause) = &mut opt.idle_after_pause
+            && *idle_after_pause < 0.0
+        {
+            *idle_after_pause = 0.0;
+            log_warning("Clamped \"idle-after-pause\" to minimum of 0!");
+        }
+
+        if opt.pixel_shift_interval < 1.0 {
+            opt.pixel_shift_interval = 1.0;
+            log_warning("Clamped \"pixel-shift-interval\" to minimum of 1!");
+        }
+        if let Some(dim_after) = &mut opt.dim_after
+            && *dim_after < 0.0
+        {
+            *dim_after = 0.0;
+            log_warning("Clamped \"dim-after\" to minimum of 0!");
+        }
+        if opt.dim_brightness > 100 {
+            opt.dim_brightness = 100;
+            log_warning("Clamped \"dim-brightness\" to maximum of 100!");
+        }
+
+        if opt.art_max_size == Some(0) {
+            opt.art_max_size = Some(1);
+            log_warning("Clamped \"art-max-size\" to minimum of 1!");
+        }
+
+        if opt.wrap_lines == 0 {
+            opt.wrap_lines = 1;
+            log_warning("Clamped \"wrap-lines\" to minimum of 1!");
+        }
+        if opt.art_backdrop_blur < 0.0 {
+            opt.art_backdrop_blur = 0.0;
+            log_warning("Clamped \"art-backdrop-blur\" to minimum of 0!");
+        }
+        if opt.art_backdrop_brightness > 100 {
+            opt.art_backdrop_brightness = 100;
+            log_warning("Clamped \"art-backdrop-brightness\" to maximum of 100!");
+        }
+
+        // Read password from file if exists, error otherwise.
+        if let Some(psswd_file_path) = opt.password_file.as_ref() {
+            let mut content: String = String::new();
+            File::open(psswd_file_path)
+                .and_then(|mut file| file.read_to_string(&mut content))
+                .map_err(|e| format!("Failed to read pfile/password_file: {e}"))?;
+
+            if content.ends_with("\r\n") {
+                content.truncate(content.len() - 2);
+            } else if content.ends_with('\n') {
+                content.truncate(content.len() - 1);
+            }
+
+            opt.password = Some(content);
+        }
+
+        opt.theme = style.theme.resolve(
+            opt.text_bg_opacity,
+            self.path.as_ref().and_then(|path| path.parent()),
+        );
+        opt.layout = style.layout.resolve()?;
+        opt.templates = style.templates.resolve()?;
+
+        Ok(opt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn matches_from(args: &[&str]) -> ArgMatches {
+        Opt::command()
+            .try_get_matches_from(args)
+            .expect("Should be able to parse args")
+    }
+
+    #[test]
+    fn test_unknown_key_has_line_number() {
+        let err = parse_config("port = 6601\n\n[theme]\ntext-colour = \"#ffffff\"\n")
+            .expect_err("Unknown key should be rejected");
+        assert!(err.contains("line 4"), "{err}");
+        assert!(err.contains("text-colour"), "{err}");
+
+        let err = parse_config("[theme]\ntext-color = \"white\"\n")
+            .expect_err("Invalid color should be rejected");
+        assert!(err.contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn test_profile_and_cli_precedence() {
+        let config = parse_config(
+            r##"
+host = "10.0.0.2"
+port = 6601
+disable-show-album = true
+text-bg-opacity = 100
+
+[profiles.kitchen]
+host = "10.0.0.3"
+disable-show-filename = true
+
+[profiles.kitchen.theme]
+text-bg-color = "#102030"
+"##,
+        )
+        .expect("Should be able to parse config");
+
+        let matches = matches_from(&["mpd_info_screen", "--disable-show-title", "-t", "50"]);
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+        let mut style = PendingStyle::default();
+        config.apply(&mut opt, &mut style, &matches);
+        config.profiles.as_ref().unwrap()["kitchen"].apply(&mut opt, &mut style, &matches);
+
+        assert_eq!(opt.host, Some(Host::Ip(Ipv4Addr::new(10, 0, 0, 3))));
+        assert_eq!(opt.port, 6601);
+        assert!(opt.disable_show_title);
+        assert!(opt.disable_show_album);
+        assert!(opt.disable_show_filename);
+        assert_eq!(opt.text_bg_opacity, 50);
+        assert_eq!(
+            style.theme.resolve(50, None).title.text_bg_color,
+            Color::from_rgba(0x10, 0x20, 0x30, 50)
+        );
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(
+            "192.168.1.6".parse(),
+            Ok(Host::Ip(Ipv4Addr::new(192, 168, 1, 6)))
+        );
+        assert_eq!(
+            "/run/mpd/socket".parse(),
+            Ok(Host::Socket(PathBuf::from("/run/mpd/socket")))
+        );
+        assert!("localhost".parse::<Host>().is_err());
+        let config =
+            parse_config(r#"host = "/var/run/mpd.sock""#).expect("Should be able to parse config");
+        assert_eq!(
+            config.host,
+            Some(Host::Socket(PathBuf::from("/var/run/mpd.sock")))
+        );
+    }
+
+    #[test]
+    fn test_art_sources() {
+        let matches = matches_from(&["mpd_info_screen"]);
+        let opt = Opt::from_arg_matches(&matches).unwrap();
+        assert_eq!(
+            opt.art_sources,
+            [
+                ArtSource::Embedded,
+                ArtSource::MpdFolder,
+                ArtSource::LocalDir,
+                ArtSource::Placeholder
+            ]
+        );
+        assert_eq!(opt.art_names[0], "cover.*");
+
+        let config = parse_config(
+            r#"
+art-sources = ["local-dir", "placeholder"]
+art-names = ["Folder.jpg"]
+music-dir = "/srv/music"
+"#,
+        )
+        .expect("Should be able to parse config");
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+        config.apply(&mut opt, &mut PendingStyle::default(), &matches);
+        assert_eq!(
+            opt.art_sources,
+            [ArtSource::LocalDir, ArtSource::Placeholder]
+        );
+        assert_eq!(opt.art_names, ["Folder.jpg"]);
+        assert_eq!(opt.music_dir, Some(PathBuf::from("/srv/music")));
+
+        let matches = matches_from(&["mpd_info_screen", "--art-sources", "mpd-folder,embedded"]);
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+        config.apply(&mut opt, &mut PendingStyle::default(), &matches);
+        assert_eq!(opt.art_sources, [ArtSource::MpdFolder, ArtSource::Embedded]);
+    }
+
+    #[test]
+    fn test_window_title() {
+        let matches = matches_from(&["mpd_info_screen"]);
+        let opt = Opt::from_arg_matches(&matches).unwrap();
+        let lookup = |name: &str| match name {
+            "artist" => Some("Artist"),
+            "title" => Some("Title"),
+            "state" => Some("paused"),
+            _ => None,
+        };
+        assert_eq!(opt.window_title.render(lookup), "Artist – Title (paused)");
+
+        let config =
+            parse_config(r#"window-title = "{title}""#).expect("Should be able to parse config");
+        let mut opt = Opt::from_arg_matches(&matches).unwrap();
+        config.apply(&mut opt, &mut PendingStyle::default(), &matches);
+        assert_eq!(opt.window_title.render(lookup), "Title");
+        assert!(parse_config(r#"window-title = "{title""#).is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let (width, height) = (800.0, 480.0);
+        assert_eq!(
+            Rotation::Clockwise.logical_size(width, height),
+            (480.0, 800.0)
+        );
+        // the top left corner of the picture ends up in the top right corner
+        assert_eq!(
+            Rotation::Clockwise.to_logical(800.0, 0.0, width, height),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            Rotation::CounterClockwise.to_logical(0.0, 480.0, width, height),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            Rotation::UpsideDown.to_logical(700.0, 80.0, width, height),
+            (100.0, 400.0)
+        );
+        let config = parse_config("rotate = 270").expect("Should be able to parse config");
+        assert_eq!(config.rotate, Some(Rotation::CounterClockwise));
+        assert!(parse_config("rotate = 45").is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let config = parse_config(
+            r#"
+[layout]
+order = ["title", "artist", "timer"]
+align = "center"
+anchor = "top"
+split = "auto"
+split-side = "right"
+
+[layout.heights]
+title = 0.2
+"#,
+        )
+        .expect("Should be able to parse config");
+        let layout = config.layout.as_ref().unwrap().resolve().unwrap();
+        assert_eq!(
+            layout.order,
+            vec![TextRow::Title, TextRow::Artist, TextRow::Timer]
+        );
+        assert_eq!(layout.align, HorizontalAlign::Center);
+        assert_eq!(layout.anchor, VerticalAnchor::Top);
+        assert_eq!(layout.height_ratio(TextRow::Title, None), 0.2);
+        assert_eq!(
+            layout.height_ratio(TextRow::Artist, None),
+            DEFAULT_TEXT_HEIGHT_RATIO
+        );
+        assert_eq!(layout.height_ratio(TextRow::Title, Some(0.1)), 0.1);
+        assert_eq!(layout.split_side_for(1600.0, 1200.0), None);
+        assert_eq!(
+            layout.split_side_for(2560.0, 1080.0),
+            Some(SplitSide::Right)
+        );
+
+        let config = parse_config("[layout]\norder = [\"title\", \"title\"]\n").unwrap();
+        assert!(config.layout.as_ref().unwrap().resolve().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use clap::ValueEnum;
+use serde::Deserialize;
 use std::fmt::Display;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -9,7 +10,8 @@ pub enum LogState {
     Verbose,
 }
 
-#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Error,
     Warning,
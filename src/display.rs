@@ -1,39 +1,61 @@
 use crate::Opt;
+use crate::album_art::{self, ArtDecodeJob, DecodeSettings, DecodedArt};
+use crate::booklet::Booklet;
+use crate::burn_in;
+use crate::clock::{self, LocalTime};
+use crate::config::{
+    ArtFit, ArtSource, ConfigSource, HorizontalAlign, Rotation, SplitSide, TextOverflow, TextRow,
+    Theme, VerticalAnchor,
+};
 use crate::debug_log::{self, log};
-use crate::mpd_handler::{InfoFromShared, MPDHandler, MPDHandlerState, MPDPlayState};
+use crate::gesture::{Gesture, GestureRecognizer, GestureThresholds};
+use crate::mpd_handler::{InfoFromShared, MPDCommand, MPDHandler, MPDPlayState, MPDStats};
+use crate::transition::{Transition, TransitionFrame, TransitionKind};
+use crate::window;
 use ggez::event::EventHandler;
 use ggez::graphics::{
-    self, Color, DrawMode, DrawParam, Drawable, Image, Mesh, MeshBuilder, PxScale, Rect, Text,
-    TextFragment, Transform,
+    Canvas, Color, DrawMode, DrawParam, Drawable, Image, Mesh, MeshBuilder, PxScale, Rect, Sampler,
+    Text, TextFragment, Transform,
 };
 use ggez::input::keyboard::{self, KeyInput};
+use ggez::input::mouse::MouseButton;
 use ggez::mint::Vector2;
+use ggez::winit::event::TouchPhase;
 use ggez::winit::keyboard::PhysicalKey;
 use ggez::{Context, GameError, GameResult};
-use image::DynamicImage;
-use image::ImageReader;
-use std::io::Cursor;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
-use std::sync::{Arc, RwLockReadGuard, atomic::Ordering};
+use std::sync::{Arc, atomic::Ordering};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 const POLL_TIME: Duration = Duration::from_millis(333);
 const INIT_FONT_SIZE_RATIO: f32 = 1.4167;
 const INIT_FONT_SIZE_X: f32 = 36.0;
 const INIT_FONT_SIZE_Y: f32 = INIT_FONT_SIZE_X * INIT_FONT_SIZE_RATIO;
-const TEXT_X_OFFSET: f32 = 0.3;
-const TEXT_OFFSET_Y_SPACING: f32 = 0.4;
-const TEXT_HEIGHT_SCALE: f32 = 0.12;
-const ARTIST_HEIGHT_SCALE: f32 = 0.12;
-const ALBUM_HEIGHT_SCALE: f32 = 0.12;
-const TIMER_HEIGHT_SCALE_RATIO: f32 = 0.875;
-const TIMER_HEIGHT_SCALE: f32 = TEXT_HEIGHT_SCALE * TIMER_HEIGHT_SCALE_RATIO;
 const MIN_WIDTH_RATIO: f32 = 4.0 / 5.0;
 const INCREASE_AMT: f32 = 6.0 / 5.0;
 const DECREASE_AMT: f32 = 5.0 / 6.0;
+/// Heights of the idle screen clock, date, last song and stats rows relative
+/// to the window height.
+const IDLE_ROW_HEIGHT_RATIOS: [f32; 4] = [0.22, 0.07, 0.06, 0.045];
+const IDLE_ROW_SPACING_RATIO: f32 = 0.02;
+const IDLE_DIM_ALPHA: f32 = 0.6;
 const RESIZE_TICKS_MAX: u32 = 200;
+const PROGRESS_BAR_HEIGHT_RATIO: f32 = 0.15;
+const SEEK_SEND_INTERVAL: Duration = Duration::from_millis(250);
+const VOLUME_STEP: i32 = 5;
+const TOUCH_VOLUME_STEP: i32 = 10;
+const GESTURE_ACK_DURATION: f32 = 1.2;
+const GESTURE_ACK_FADE_TIME: f32 = 0.4;
+const GESTURE_ACK_HEIGHT_SCALE: f32 = 0.08;
+const CONFIG_CHECK_TIME: Duration = Duration::from_secs(1);
+/// How long the window size must stay unchanged for before downscaled album
+/// art is decoded again for the new size.
+const ART_REDECODE_DELAY: Duration = Duration::from_millis(500);
+/// The album art panel of the split layout is square, unless that would take
+/// more than this fraction of the window width.
+const MAX_SPLIT_ART_WIDTH_RATIO: f32 = 0.6;
 
 fn seconds_to_time(seconds: f64) -> String {
     let seconds_int: u64 = seconds.floor() as u64;
@@ -58,23 +80,172 @@ fn seconds_to_time(seconds: f64) -> String {
     result
 }
 
+fn gesture_thresholds(opts: &Opt) -> GestureThresholds {
+    GestureThresholds {
+        swipe_distance: opts.touch_swipe_distance,
+        tap_distance: opts.touch_tap_distance,
+        long_press: Duration::from_millis(opts.touch_long_press_ms),
+    }
+}
+
+/// The MPD stats are only fetched when the idle screen can show them.
+fn is_fetching_stats(opts: &Opt) -> bool {
+    opts.idle_stats && (opts.idle_screen || opts.idle_after_pause.is_some())
+}
+
+fn fade(color: Color, alpha: f32) -> Color {
+    Color {
+        a: color.a * alpha,
+        ..color
+    }
+}
+
+fn shift_transform(transform: Transform, offset_x: f32) -> Transform {
+    match transform {
+        Transform::Values {
+            dest,
+            rotation,
+            scale,
+            offset,
+        } => Transform::Values {
+            dest: [dest.x + offset_x, dest.y].into(),
+            rotation,
+            scale,
+            offset,
+        },
+        Transform::Matrix(mut matrix) => {
+            matrix.w.x += offset_x;
+            Transform::Matrix(matrix)
+        }
+    }
+}
+
+/// Moves a draw by the burn-in protection offset, after its own transform.
+fn burn_in_param(param: DrawParam, shift: [f32; 2]) -> DrawParam {
+    let transform = match param.transform {
+        Transform::Values {
+            dest,
+            rotation,
+            scale,
+            offset,
+        } => Transform::Values {
+            dest: [dest.x + shift[0], dest.y + shift[1]].into(),
+            rotation,
+            scale,
+            offset,
+        },
+        Transform::Matrix(mut matrix) => {
+            matrix.w.x += shift[0];
+            matrix.w.y += shift[1];
+            Transform::Matrix(matrix)
+        }
+    };
+    DrawParam { transform, ..param }
+}
+
+fn transform_dest(transform: &Transform) -> (f32, f32) {
+    match transform {
+        Transform::Values { dest, .. } => (dest.x, dest.y),
+        Transform::Matrix(matrix) => (matrix.w.x, matrix.w.y),
+    }
+}
+
+/// Album art that is still shown while the art of the next song is loaded
+/// and transitioned to.
+struct OldAlbumArt {
+    image: Image,
+    transform: Transform,
+    backdrop: Option<(Image, Transform)>,
+}
+
+/// The text of the previous song, drawn while transitioning away from it.
+struct OldText {
+    rows: Vec<(Text, Transform, Color)>,
+    bg_mesh: Option<Mesh>,
+}
+
+/// Horizontal offset of a row that is `overflow` pixels wider than the space
+/// it has, scrolling back and forth with a pause at either end.
+fn marquee_offset(overflow: f32, elapsed: f32, speed: f32, pause: f32) -> f32 {
+    if overflow <= 0.0 || speed <= 0.0 {
+        return 0.0;
+    }
+    let scroll_time = overflow / speed;
+    let t = elapsed % (2.0 * (pause + scroll_time));
+    let position = if t < pause {
+        0.0
+    } else if t < pause + scroll_time {
+        (t - pause) * speed
+    } else if t < 2.0 * pause + scroll_time {
+        overflow
+    } else {
+        overflow - (t - 2.0 * pause - scroll_time) * speed
+    };
+    -position
+}
+
 fn time_to_percentage(total: f64, current: f64) -> String {
     ((100.0f64 * current / total).round() as i32).to_string() + "%"
 }
 
+/// Loads the custom font at the given path on first use, returning the
+/// path and the name it was added to ggez as.
+fn get_custom_font(
+    font_path: Option<&PathBuf>,
+    custom_fonts: &mut Vec<(PathBuf, Option<String>)>,
+    ctx: &mut Context,
+    log_level: debug_log::LogLevel,
+) -> Option<(PathBuf, String)> {
+    let path = font_path?;
+    if let Some((_, font_name)) = custom_fonts.iter().find(|(p, _)| p == path) {
+        return font_name.clone().map(|font_name| (path.clone(), font_name));
+    }
+
+    let font_name = format!("custom:{}", path.display());
+    match ggez::graphics::FontData::from_path(ctx, path) {
+        Ok(font) => {
+            ctx.gfx.add_font(&font_name, font);
+            custom_fonts.push((path.clone(), Some(font_name.clone())));
+            log(
+                format!("Loaded custom font {path:?}"),
+                debug_log::LogState::Debug,
+                log_level,
+            );
+            Some((path.clone(), font_name))
+        }
+        Err(e) => {
+            log(
+                format!("Failed to load custom font {path:?}: {e}"),
+                debug_log::LogState::Error,
+                log_level,
+            );
+            custom_fonts.push((path.clone(), None));
+            None
+        }
+    }
+}
+
 #[cfg(not(feature = "unicode_support"))]
 #[allow(clippy::ptr_arg)]
 fn string_to_text(
     string: String,
+    primary_font: Option<&(PathBuf, String)>,
     _loaded_fonts: &mut Vec<(PathBuf, String)>,
     _ctx: &mut Context,
 ) -> Text {
-    Text::new(TextFragment::from(string))
+    let mut fragment = TextFragment::from(string);
+    if let Some((_, font_name)) = primary_font {
+        fragment = fragment.font(font_name.clone());
+    }
+    Text::new(fragment)
 }
 
+/// Characters missing from the primary font (or the default font, if there is
+/// no primary font) fall back to fonts found through fontconfig.
 #[cfg(feature = "unicode_support")]
 fn string_to_text(
     string: String,
+    primary_font: Option<&(PathBuf, String)>,
     loaded_fonts: &mut Vec<(PathBuf, String)>,
     ctx: &mut Context,
 ) -> Text {
@@ -82,9 +253,11 @@ fn string_to_text(
 
     let mut text = Text::default();
     let mut current_fragment = TextFragment::default();
+    let primary_font_name = primary_font.map(|(_, font_name)| font_name.clone());
 
     if string.is_ascii() {
         current_fragment.text = string;
+        current_fragment.font = primary_font_name;
         text.add(current_fragment);
         return text;
     }
@@ -129,63 +302,23 @@ fn string_to_text(
             None
         };
 
-    let mut prev_is_ascii = true;
     for c in string.chars() {
-        if c.is_ascii() {
-            if prev_is_ascii {
-                current_fragment.text.push(c);
-            } else {
-                if !current_fragment.text.is_empty() {
-                    text.add(current_fragment);
-                    current_fragment = Default::default();
-                }
-                current_fragment.text.push(c);
-            }
-            prev_is_ascii = true;
+        let font: Option<String> = if c.is_ascii() {
+            primary_font_name.clone()
+        } else if let Some((path, font_name)) = primary_font
+            && unicode_support::font_has_char(c, path).unwrap_or(false)
+        {
+            Some(font_name.clone())
         } else {
-            let idx_opt = find_font(c, loaded_fonts, ctx);
-            if prev_is_ascii {
-                if let Some(idx) = idx_opt {
-                    if !current_fragment.text.is_empty() {
-                        text.add(current_fragment);
-                        current_fragment = Default::default();
-                    }
-                    let (_, font) = &loaded_fonts[idx];
-                    current_fragment.font = Some(font.clone());
-                }
-                current_fragment.text.push(c);
-            } else if let Some(idx) = idx_opt {
-                let font = &loaded_fonts[idx].1;
-                if let Some(current_font) = current_fragment.font.as_ref() {
-                    if current_font == font {
-                        current_fragment.text.push(c);
-                    } else {
-                        if !current_fragment.text.is_empty() {
-                            text.add(current_fragment);
-                            current_fragment = Default::default();
-                        }
-                        current_fragment.text.push(c);
-                        current_fragment.font = Some(font.clone());
-                    }
-                } else if current_fragment.text.is_empty() {
-                    current_fragment.text.push(c);
-                    current_fragment.font = Some(font.clone());
-                } else {
-                    text.add(current_fragment);
-                    current_fragment = Default::default();
+            find_font(c, loaded_fonts, ctx).map(|idx| loaded_fonts[idx].1.clone())
+        };
 
-                    current_fragment.text.push(c);
-                    current_fragment.font = Some(font.clone());
-                }
-            } else {
-                if !current_fragment.text.is_empty() && current_fragment.font.is_some() {
-                    text.add(current_fragment);
-                    current_fragment = Default::default();
-                }
-                current_fragment.text.push(c);
-            }
-            prev_is_ascii = false;
+        if font != current_fragment.font && !current_fragment.text.is_empty() {
+            text.add(current_fragment);
+            current_fragment = Default::default();
         }
+        current_fragment.font = font;
+        current_fragment.text.push(c);
     }
 
     if !current_fragment.text.is_empty() {
@@ -202,13 +335,33 @@ pub struct MPDDisplay {
     is_initialized: bool,
     is_authenticated: bool,
     notice_text: Text,
+    art_notice_text: Text,
     poll_instant: Instant,
     shared: Option<InfoFromShared>,
     password_entered: bool,
     dirty_flag: Option<Arc<AtomicBool>>,
     album_art: Option<Image>,
     album_art_draw_transform: Option<Transform>,
+    album_art_backdrop: Option<Image>,
+    album_art_backdrop_transform: Option<Transform>,
+    adaptive_theme: Option<Theme>,
+    is_bg_mesh_adaptive: bool,
+    old_album_art: Option<OldAlbumArt>,
+    art_transition: Option<Transition>,
+    art_decode: Option<ArtDecodeJob>,
+    is_album_art_downscaled: bool,
+    is_art_redecode_needed: bool,
+    resize_instant: Instant,
+    /// Every frame of animated album art with its delay in seconds.
+    album_art_frames: Vec<(Image, f32)>,
+    art_frame_idx: usize,
+    art_frame_elapsed: f32,
+    old_text: Option<OldText>,
+    text_transition: Option<Transition>,
+    marquee_elapsed: f32,
+    is_marquee_scrolling: bool,
     filename_text: Text,
+    song_filename: String,
     filename_string_cache: String,
     filename_transform: Transform,
     artist_text: Text,
@@ -227,23 +380,49 @@ pub struct MPDDisplay {
     timer_y: f32,
     timer: f64,
     length: f64,
-    cached_filename_y: f32,
-    cached_album_y: f32,
-    cached_artist_y: f32,
-    cached_title_y: f32,
-    cached_timer_y: f32,
     text_bg_mesh: Option<Mesh>,
+    timer_bg_mesh: Option<Mesh>,
     hide_text: bool,
-    tried_album_art_in_dir: bool,
+    /// Index into "art-sources" of the source album art is taken from.
+    art_source_idx: usize,
+    booklet: Option<Booklet>,
+    /// The window title last set from "window-title".
+    window_title: String,
+    is_window_icon_set: bool,
+    /// The scene before it is turned onto the window by "rotate".
+    rotated_scene: Option<Image>,
+    /// When MPD last started or stopped playing, for "dim-after".
+    play_state_instant: Instant,
+    /// When MPD was first seen paused, for "idle-after-pause".
+    paused_instant: Option<Instant>,
+    /// Title and artist of the last song played, shown on the idle screen.
+    last_song: String,
+    /// What the idle rows were built from, to rebuild them only on changes.
+    idle_strings: Vec<String>,
+    idle_size: (f32, f32),
+    idle_rows: Vec<(Text, [f32; 2], Color)>,
     prev_mpd_play_state: MPDPlayState,
     mpd_play_state: MPDPlayState,
     loaded_fonts: Vec<(PathBuf, String)>,
+    custom_fonts: Vec<(PathBuf, Option<String>)>,
     close_request_handled: bool,
     resize_ticks: u32,
+    is_low_fps: bool,
+    is_seeking: bool,
+    seek_send_instant: Instant,
+    volume: i32,
+    gesture_recognizer: GestureRecognizer,
+    gesture_ack_text: Text,
+    gesture_ack_remaining: f32,
+    config_source: ConfigSource,
+    config_modified_time: Option<SystemTime>,
+    config_check_instant: Instant,
 }
 
 impl MPDDisplay {
-    pub fn new(_ctx: &mut Context, opts: Opt) -> Self {
+    pub fn new(_ctx: &mut Context, opts: Opt, config_source: ConfigSource) -> Self {
+        let gesture_thresholds = gesture_thresholds(&opts);
+        let config_modified_time = config_source.modified_time();
         Self {
             opts,
             mpd_handler: Err(String::from("Uninitialized")),
@@ -251,12 +430,30 @@ impl MPDDisplay {
             is_initialized: false,
             is_authenticated: false,
             notice_text: Text::default(),
+            art_notice_text: Text::default(),
             poll_instant: Instant::now().checked_sub(POLL_TIME).unwrap(),
             shared: None,
             password_entered: false,
             dirty_flag: None,
             album_art: None,
             album_art_draw_transform: None,
+            album_art_backdrop: None,
+            album_art_backdrop_transform: None,
+            adaptive_theme: None,
+            is_bg_mesh_adaptive: false,
+            old_album_art: None,
+            art_transition: None,
+            art_decode: None,
+            is_album_art_downscaled: false,
+            is_art_redecode_needed: false,
+            resize_instant: Instant::now(),
+            album_art_frames: Vec::new(),
+            art_frame_idx: 0,
+            art_frame_elapsed: 0.0,
+            old_text: None,
+            text_transition: None,
+            marquee_elapsed: 0.0,
+            is_marquee_scrolling: false,
             filename_text: Text::default(),
             filename_transform: Transform::default(),
             artist_text: Text::default(),
@@ -270,17 +467,25 @@ impl MPDDisplay {
             timer_y: INIT_FONT_SIZE_Y,
             timer: 0.0,
             length: 0.0,
-            cached_filename_y: 0.0f32,
-            cached_album_y: 0.0f32,
-            cached_artist_y: 0.0f32,
-            cached_title_y: 0.0f32,
-            cached_timer_y: 0.0f32,
             text_bg_mesh: None,
+            timer_bg_mesh: None,
             hide_text: false,
-            tried_album_art_in_dir: false,
+            art_source_idx: 0,
+            booklet: None,
+            window_title: String::from(window::DEFAULT_TITLE),
+            is_window_icon_set: false,
+            rotated_scene: None,
+            play_state_instant: Instant::now(),
+            paused_instant: None,
+            last_song: String::new(),
+            idle_strings: Vec::new(),
+            idle_size: (0.0, 0.0),
+            idle_rows: Vec::new(),
             prev_mpd_play_state: MPDPlayState::Playing,
             mpd_play_state: MPDPlayState::Playing,
             loaded_fonts: Vec::new(),
+            custom_fonts: Vec::new(),
+            song_filename: String::new(),
             filename_string_cache: String::new(),
             artist_string_cache: String::new(),
             title_string_cache: String::new(),
@@ -289,14 +494,29 @@ impl MPDDisplay {
             album_transform: Transform::default(),
             close_request_handled: false,
             resize_ticks: 0,
+            is_low_fps: false,
+            is_seeking: false,
+            seek_send_instant: Instant::now().checked_sub(SEEK_SEND_INTERVAL).unwrap(),
+            volume: -1,
+            gesture_recognizer: GestureRecognizer::new(gesture_thresholds),
+            gesture_ack_text: Text::default(),
+            gesture_ack_remaining: 0.0,
+            config_source,
+            config_modified_time,
+            config_check_instant: Instant::now(),
         }
     }
 
     fn init_mpd_handler(&mut self) {
         self.mpd_handler = MPDHandler::new(
-            self.opts.host,
+            self.opts
+                .host
+                .clone()
+                .expect("host should be set after loading the config"),
             self.opts.port,
             self.opts.password.clone().map_or(String::new(), |s| s),
+            &self.opts.art_sources,
+            is_fetching_stats(&self.opts),
             self.opts.log_level,
         );
         if let Ok(mpd_h) = &self.mpd_handler {
@@ -324,46 +544,184 @@ impl MPDDisplay {
         }
     }
 
-    fn get_album_art_transform(&mut self, ctx: &mut Context, fill_scaled: bool) {
-        if fill_scaled {
-            if let Some(image) = &self.album_art {
-                let drawable_size = ctx.gfx.drawable_size();
-                let art_rect: Rect = image.dimensions(ctx);
-
-                // try to fit to width first
-                let mut x_scale = drawable_size.0 / art_rect.w;
-                let mut y_scale = x_scale;
-                let mut new_width = art_rect.w * x_scale;
-                let mut new_height = art_rect.h * y_scale;
-                if new_height > drawable_size.1.abs() {
-                    // fit to height instead
-                    y_scale = drawable_size.1.abs() / art_rect.h;
-                    x_scale = y_scale;
-                    new_width = art_rect.w * x_scale;
-                    new_height = art_rect.h * y_scale;
+    fn stop_mpd_handler(&mut self) {
+        if let Ok(mpd_handler) = &self.mpd_handler {
+            loop {
+                let stop_thread_result = mpd_handler.stop_thread();
+                if stop_thread_result.is_ok() {
+                    break;
                 }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        self.mpd_handler = Err(String::from("Uninitialized"));
+        self.dirty_flag = None;
+        self.shared = None;
+        self.is_initialized = false;
+        self.is_authenticated = false;
+        self.password_entered = false;
+        self.mpd_play_state = MPDPlayState::Stopped;
+        self.clear_song_info();
+        if self.opts.enable_prompt_password {
+            self.opts.password = None;
+        }
+        self.notice_text = Text::default();
+    }
+
+    fn clear_song_info(&mut self) {
+        self.title_text = Text::default();
+        self.artist_text = Text::default();
+        self.album_text = Text::default();
+        self.filename_text = Text::default();
+        self.timer = 0.0;
+        self.length = 0.0;
+        self.album_art = None;
+        self.album_art_frames.clear();
+        self.album_art_draw_transform = None;
+        self.old_album_art = None;
+        self.art_transition = None;
+        self.art_decode = None;
+        self.art_notice_text = Text::default();
+        self.old_text = None;
+        self.text_transition = None;
+        self.song_filename.clear();
+        self.filename_string_cache.clear();
+        self.artist_string_cache.clear();
+        self.title_string_cache.clear();
+        self.album_string_cache.clear();
+    }
+
+    fn check_config_reload(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let mut reload = crate::signal::RELOAD_REQUESTED.swap(false, Ordering::Relaxed);
+        if self.config_check_instant.elapsed() > CONFIG_CHECK_TIME {
+            self.config_check_instant = Instant::now();
+            let modified_time = self.config_source.modified_time();
+            if modified_time != self.config_modified_time {
+                self.config_modified_time = modified_time;
+                reload = true;
+            }
+        }
+        if reload {
+            self.reload_config(ctx)?;
+        }
 
-                let offset_x: f32 = (drawable_size.0.abs() - new_width) / 2.0f32;
-                let offset_y: f32 = (drawable_size.1.abs() - new_height) / 2.0f32;
+        Ok(())
+    }
 
-                self.album_art_draw_transform = Some(Transform::Values {
-                    dest: [offset_x, offset_y].into(),
-                    rotation: 0.0f32,
-                    scale: [x_scale, y_scale].into(),
-                    offset: [0.0f32, 0.0f32].into(),
-                });
-            } else {
-                self.album_art_draw_transform = None;
+    fn reload_config(&mut self, ctx: &mut Context) -> GameResult<()> {
+        log(
+            "Reloading config",
+            debug_log::LogState::Warning,
+            self.opts.log_level,
+        );
+        let mut new_opts = match self.config_source.load() {
+            Ok(new_opts) => new_opts,
+            Err(e) => {
+                log(
+                    format!("Failed to reload config, keeping the current one: {e}"),
+                    debug_log::LogState::Error,
+                    self.opts.log_level,
+                );
+                return Ok(());
+            }
+        };
+
+        if new_opts.enable_prompt_password && self.opts.enable_prompt_password {
+            // keep the password that was typed in at the prompt
+            new_opts.password = self.opts.password.clone();
+        }
+        // the adaptive theme is built on top of the theme, which may have changed
+        let is_art_processing_changed = new_opts.art_backdrop != self.opts.art_backdrop
+            || new_opts.art_backdrop_blur != self.opts.art_backdrop_blur
+            || new_opts.art_backdrop_brightness != self.opts.art_backdrop_brightness
+            || new_opts.adaptive_theme
+            || self.opts.adaptive_theme;
+        let is_local_art_changed =
+            new_opts.music_dir != self.opts.music_dir || new_opts.art_names != self.opts.art_names;
+        let is_booklet_changed = is_local_art_changed
+            || new_opts.booklet_slideshow != self.opts.booklet_slideshow
+            || new_opts.booklet_scans != self.opts.booklet_scans;
+        let is_art_decoding_changed = new_opts.art_fit != self.opts.art_fit
+            || new_opts.art_max_size != self.opts.art_max_size
+            || new_opts.disable_art_animation != self.opts.disable_art_animation
+            || new_opts.art_animation_memory != self.opts.art_animation_memory
+            || new_opts.layout.split != self.opts.layout.split
+            || new_opts.rotate != self.opts.rotate;
+        let is_connection_changed = new_opts.host != self.opts.host
+            || new_opts.port != self.opts.port
+            || new_opts.password != self.opts.password
+            || new_opts.enable_prompt_password != self.opts.enable_prompt_password
+            || new_opts.log_level != self.opts.log_level
+            // the handler fetches the sources through MPD in this order
+            || new_opts.art_sources != self.opts.art_sources
+            || is_fetching_stats(&new_opts) != is_fetching_stats(&self.opts);
+
+        self.opts = new_opts;
+        // retry fonts that failed to load, they may have been fixed since
+        self.custom_fonts
+            .retain(|(_, font_name)| font_name.is_some());
+        self.gesture_recognizer = GestureRecognizer::new(gesture_thresholds(&self.opts));
+        self.resize_ticks = 0;
+
+        if is_connection_changed {
+            log(
+                "Connection settings changed, restarting MPDHandler",
+                debug_log::LogState::Warning,
+                self.opts.log_level,
+            );
+            self.stop_mpd_handler();
+        } else {
+            // Rebuild the text on the next poll so that it picks up any changes
+            self.filename_string_cache.clear();
+            self.artist_string_cache.clear();
+            self.title_string_cache.clear();
+            self.album_string_cache.clear();
+            if is_booklet_changed {
+                self.booklet = None;
+                self.update_booklet();
+            }
+            if is_local_art_changed {
+                // a source skipped before may have art now
+                self.take_old_album_art();
+                self.art_source_idx = 0;
+            } else if is_art_processing_changed {
+                // decode the album art again to rebuild what is derived from it
+                self.take_old_album_art();
+                if let Some(booklet) = &mut self.booklet {
+                    booklet.restart();
+                }
+            } else if is_art_decoding_changed {
+                self.is_art_redecode_needed = true;
             }
-        } else if let Some(image) = &self.album_art {
-            let drawable_size = ctx.gfx.drawable_size();
+            if let Some(dirty_flag) = &self.dirty_flag {
+                dirty_flag.store(true, Ordering::Release);
+            }
+        }
+
+        self.get_album_art_transform(ctx);
+        self.refresh_text_transforms(ctx)
+    }
+
+    fn get_album_art_transform(&mut self, ctx: &mut Context) {
+        self.get_album_art_backdrop_transform(ctx);
+        if let Some(image) = &self.album_art {
+            let area = self.get_art_area(ctx);
             let art_rect: Rect = image.dimensions(ctx);
-            let offset_x: f32 = (drawable_size.0.abs() - art_rect.w.abs()) / 2.0f32;
-            let offset_y: f32 = (drawable_size.1.abs() - art_rect.h.abs()) / 2.0f32;
+            let (x_scale, y_scale) =
+                album_art::art_fit_scale(self.opts.art_fit, art_rect.w, art_rect.h, &area);
+            let new_width = art_rect.w * x_scale;
+            let new_height = art_rect.h * y_scale;
+
+            // the anchor decides where the art goes in the leftover space, or
+            // which part of it is cropped
+            let (anchor_x, anchor_y) = self.opts.art_anchor.fractions();
+            let offset_x: f32 = area.x + (area.w - new_width) * anchor_x;
+            let offset_y: f32 = area.y + (area.h - new_height) * anchor_y;
+
             self.album_art_draw_transform = Some(Transform::Values {
                 dest: [offset_x, offset_y].into(),
                 rotation: 0.0f32,
-                scale: [1.0f32, 1.0f32].into(),
+                scale: [x_scale, y_scale].into(),
                 offset: [0.0f32, 0.0f32].into(),
             });
         } else {
@@ -371,387 +729,1166 @@ impl MPDDisplay {
         }
     }
 
-    fn get_image_from_data(&mut self, ctx: &mut Context) -> Result<(), String> {
-        let mut read_guard_opt: Option<RwLockReadGuard<'_, MPDHandlerState>> = self
-            .mpd_handler
-            .as_ref()
-            .unwrap()
-            .get_state_read_guard()
-            .ok();
-        if let Some(read_guard) = &read_guard_opt {
-            if !read_guard.is_art_data_ready() {
-                return Err(String::from("MPDHandlerState does not have album art data"));
-            }
-        } else {
-            return Err(String::from("Failed to get read_guard of MPDHandlerState"));
+    /// Cropped art is kept inside its panel when the window is split, and
+    /// integer scaled art is drawn without smoothing.
+    fn draw_album_art(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        image: &Image,
+        param: DrawParam,
+    ) -> GameResult<()> {
+        let area = self.get_art_area(ctx);
+        let is_clipped = self.get_split_side(ctx).is_some() && area.w >= 1.0 && area.h >= 1.0;
+        if is_clipped {
+            canvas.set_scissor_rect(area)?;
+        }
+        if self.opts.art_fit == ArtFit::Integer {
+            canvas.set_sampler(Sampler::nearest_clamp());
         }
+        canvas.draw(image, param);
+        canvas.set_default_sampler();
+        if is_clipped {
+            canvas.set_default_scissor_rect();
+        }
+        Ok(())
+    }
 
-        let image_ref = read_guard_opt.as_ref().unwrap().get_art_data();
+    /// Returns the side of the album art if the window is wide enough to show
+    /// the art and the text side by side.
+    fn get_split_side(&self, ctx: &Context) -> Option<SplitSide> {
+        let drawable_size = self.screen_size(ctx);
+        self.opts
+            .layout
+            .split_side_for(drawable_size.0.abs(), drawable_size.1.abs())
+    }
 
-        let mut image_format: image::ImageFormat = image::ImageFormat::Png;
-        log(
-            format!(
-                "Got image_format type {}",
-                read_guard_opt.as_ref().unwrap().get_art_type()
-            ),
-            debug_log::LogState::Debug,
-            self.opts.log_level,
-        );
+    fn get_split_art_width(&self, ctx: &Context) -> f32 {
+        let drawable_size = self.screen_size(ctx);
+        drawable_size
+            .1
+            .abs()
+            .min(drawable_size.0.abs() * MAX_SPLIT_ART_WIDTH_RATIO)
+    }
 
-        let mut is_unknown_format: bool = false;
-
-        match read_guard_opt.as_ref().unwrap().get_art_type().as_str() {
-            "image/png" => image_format = image::ImageFormat::Png,
-            "image/jpg" | "image/jpeg" | "JPG" => image_format = image::ImageFormat::Jpeg,
-            "image/gif" => image_format = image::ImageFormat::Gif,
-            _ => is_unknown_format = true,
-        }
-
-        let try_second_art_fetch_method = |tried_in_dir: &mut bool,
-                                           album_art: &mut Option<Image>,
-                                           read_guard_opt: &mut Option<
-            RwLockReadGuard<'_, MPDHandlerState>,
-        >,
-                                           mpd_handler: &Result<MPDHandler, String>|
-         -> Result<(), String> {
-            *tried_in_dir = true;
-            album_art.take();
-            // Drop the "read_guard" so that the "force_try_other_album_art()"
-            // can get a "write_guard"
-            read_guard_opt.take();
-            mpd_handler
-                .as_ref()
-                .unwrap()
-                .force_try_other_album_art()
-                .map_err(|_| String::from("Failed to force try other album art fetching method"))?;
-            Err("Got unknown format album art image".into())
-        };
+    /// The size the scene is laid out at, which is the window size turned by
+    /// "rotate".
+    fn screen_size(&self, ctx: &Context) -> (f32, f32) {
+        let (width, height) = ctx.gfx.drawable_size();
+        self.opts.rotate.logical_size(width.abs(), height.abs())
+    }
 
-        if is_unknown_format && !self.tried_album_art_in_dir {
-            return try_second_art_fetch_method(
-                &mut self.tried_album_art_in_dir,
-                &mut self.album_art,
-                &mut read_guard_opt,
-                &self.mpd_handler,
-            );
-        }
+    /// Maps mouse and touch positions in the window to the scene.
+    fn to_scene_position(&self, ctx: &Context, x: f32, y: f32) -> (f32, f32) {
+        let (width, height) = ctx.gfx.drawable_size();
+        self.opts.rotate.to_logical(x, y, width.abs(), height.abs())
+    }
 
-        let img_result = if is_unknown_format {
-            let reader = ImageReader::new(Cursor::new(image_ref));
-            let guessed_reader = reader
-                .with_guessed_format()
-                .map_err(|e| format!("Error: Failed to guess format of album art image: {e}"));
-            if let Ok(reader) = guessed_reader {
-                reader.decode().map_err(|e| {
-                    format!("Error: Failed to decode album art image (guessed format): {e}")
-                })
-            } else {
-                // Convert Ok(_) to Ok(DynamicImage) which will never be used
-                // since the if statement covers it.
-                guessed_reader.map(|_| -> DynamicImage { unreachable!() })
+    /// Draws straight to the window, or to an image that is turned onto the
+    /// window by `finish_scene` when the picture is rotated.
+    fn scene_canvas(&mut self, ctx: &mut Context, clear: Color) -> Canvas {
+        if self.opts.rotate == Rotation::None {
+            self.rotated_scene = None;
+            return Canvas::from_frame(ctx, clear);
+        }
+        let (width, height) = self.screen_size(ctx);
+        let (width, height) = (width.max(1.0) as u32, height.max(1.0) as u32);
+        let scene = match &self.rotated_scene {
+            Some(scene) if scene.width() == width && scene.height() == height => scene.clone(),
+            _ => {
+                let scene = Image::new_canvas_image(ctx, width, height, 1);
+                self.rotated_scene = Some(scene.clone());
+                scene
             }
-        } else {
-            ImageReader::with_format(Cursor::new(image_ref), image_format)
-                .decode()
-                .map_err(|e| format!("Error: Failed to decode album art image: {e}"))
         };
-        if img_result.is_err() && !self.tried_album_art_in_dir {
-            return try_second_art_fetch_method(
-                &mut self.tried_album_art_in_dir,
-                &mut self.album_art,
-                &mut read_guard_opt,
-                &self.mpd_handler,
-            );
-        }
-        let img = img_result?;
-        let rgba8 = img.to_rgba8();
-        let ggez_img = Image::from_pixels(
-            ctx,
-            rgba8.as_raw(),
-            wgpu_types::TextureFormat::Rgba8UnormSrgb,
-            rgba8.width(),
-            rgba8.height(),
+        Canvas::from_image(ctx, scene, clear)
+    }
+
+    fn finish_scene(&self, ctx: &mut Context, canvas: Canvas) -> GameResult<()> {
+        canvas.finish(ctx)?;
+        let Some(scene) = &self.rotated_scene else {
+            return Ok(());
+        };
+        let (width, height) = ctx.gfx.drawable_size();
+        let mut frame = Canvas::from_frame(ctx, Color::BLACK);
+        frame.draw(
+            scene,
+            DrawParam::default()
+                .dest([width.abs() / 2.0, height.abs() / 2.0])
+                .offset([0.5, 0.5])
+                .rotation(self.opts.rotate.angle()),
         );
+        frame.finish(ctx)
+    }
 
-        self.album_art = Some(ggez_img);
+    /// The area the album art is fitted into, which is the whole window unless
+    /// it is split.
+    fn get_art_area(&self, ctx: &Context) -> Rect {
+        let drawable_size = self.screen_size(ctx);
+        let (width, height) = (drawable_size.0.abs(), drawable_size.1.abs());
+        let art_width = self.get_split_art_width(ctx);
+        match self.get_split_side(ctx) {
+            None => Rect::new(0.0, 0.0, width, height),
+            Some(SplitSide::Left) => Rect::new(0.0, 0.0, art_width, height),
+            Some(SplitSide::Right) => Rect::new(width - art_width, 0.0, art_width, height),
+        }
+    }
 
-        Ok(())
+    /// The area the text rows are laid out in, the rest of the window beside
+    /// the album art when split.
+    fn get_text_area(&self, ctx: &Context) -> Rect {
+        let drawable_size = self.screen_size(ctx);
+        let (width, height) = (drawable_size.0.abs(), drawable_size.1.abs());
+        let art_width = self.get_split_art_width(ctx);
+        match self.get_split_side(ctx) {
+            None => Rect::new(0.0, 0.0, width, height),
+            Some(SplitSide::Left) => Rect::new(art_width, 0.0, width - art_width, height),
+            Some(SplitSide::Right) => Rect::new(0.0, 0.0, width - art_width, height),
+        }
     }
 
-    fn refresh_text_transforms(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let drawable_size = ctx.gfx.drawable_size();
-
-        let text_height_scale: f32;
-        let album_height_scale: f32;
-        let artist_height_scale: f32;
-        let timer_height_scale: f32;
-
-        if let Some(forced_scale) = &self.opts.force_text_height_scale {
-            text_height_scale = *forced_scale;
-            album_height_scale = *forced_scale;
-            artist_height_scale = *forced_scale;
-            timer_height_scale = *forced_scale * TIMER_HEIGHT_SCALE_RATIO;
+    /// The backdrop is scaled to cover the whole window, cropping what does
+    /// not fit.
+    fn get_album_art_backdrop_transform(&mut self, ctx: &mut Context) {
+        if let Some(image) = &self.album_art_backdrop {
+            let drawable_size = self.screen_size(ctx);
+            let art_rect: Rect = image.dimensions(ctx);
+            let scale =
+                (drawable_size.0.abs() / art_rect.w).max(drawable_size.1.abs() / art_rect.h);
+            let offset_x: f32 = (drawable_size.0.abs() - art_rect.w * scale) / 2.0f32;
+            let offset_y: f32 = (drawable_size.1.abs() - art_rect.h * scale) / 2.0f32;
+            self.album_art_backdrop_transform = Some(Transform::Values {
+                dest: [offset_x, offset_y].into(),
+                rotation: 0.0f32,
+                scale: [scale, scale].into(),
+                offset: [0.0f32, 0.0f32].into(),
+            });
         } else {
-            text_height_scale = TEXT_HEIGHT_SCALE;
-            album_height_scale = ALBUM_HEIGHT_SCALE;
-            artist_height_scale = ARTIST_HEIGHT_SCALE;
-            timer_height_scale = TIMER_HEIGHT_SCALE;
-        }
-
-        let text_height_limit = text_height_scale * drawable_size.1.abs();
-        let album_height_limit = album_height_scale * drawable_size.1.abs();
-        let artist_height_limit = artist_height_scale * drawable_size.1.abs();
-        let timer_height = timer_height_scale * drawable_size.1.abs();
-
-        let mut offset_y: f32 = drawable_size.1;
-
-        let set_transform = |text: &mut Text,
-                             transform: &mut Transform,
-                             offset_y: &mut f32,
-                             y: &mut f32,
-                             is_string: bool,
-                             is_artist: bool,
-                             is_album: bool,
-                             timer_x: &mut f32,
-                             timer_y: &mut f32| {
-            let mut current_x = INIT_FONT_SIZE_X;
-            let mut current_y = INIT_FONT_SIZE_Y;
-            let mut width_height: Vector2<f32> = Vector2 { x: 0.0, y: 0.0 };
-            let mut iteration_count: u8 = 0;
-            loop {
-                iteration_count += 1;
-                if iteration_count > 8 {
-                    break;
-                }
+            self.album_art_backdrop_transform = None;
+        }
+    }
 
-                for fragment in text.fragments_mut() {
-                    fragment.scale = Some(PxScale {
-                        x: current_x,
-                        y: current_y,
-                    });
-                }
-                width_height = text
-                    .measure(ctx)
-                    .expect("Should be able to get width/height of text.");
+    /// The directory of the current song under "music-dir", or else under the
+    /// music directory MPD reports over its local socket.
+    fn local_song_dir(&self) -> Option<PathBuf> {
+        let shared = self.shared.as_ref()?;
+        let filename = &shared.filename;
+        // streams have URLs instead of paths relative to the music directory
+        if filename.is_empty() || filename.contains("://") {
+            return None;
+        }
+        self.opts
+            .music_dir
+            .as_ref()
+            .or(shared.music_dir.as_ref())?
+            .join(filename)
+            .parent()
+            .map(Path::to_path_buf)
+    }
 
-                if is_string {
-                    if drawable_size.0 < width_height.x
-                        || width_height.y
-                            >= (if is_artist {
-                                artist_height_limit
-                            } else if is_album {
-                                album_height_limit
-                            } else {
-                                text_height_limit
-                            })
-                    {
-                        current_x *= DECREASE_AMT;
-                        current_y *= DECREASE_AMT;
-                        continue;
-                    } else if drawable_size.0 * MIN_WIDTH_RATIO > width_height.x {
-                        current_x *= INCREASE_AMT;
-                        current_y *= INCREASE_AMT;
-                        continue;
-                    } else {
-                        break;
-                    }
-                } else {
-                    let diff_scale_y = current_y / width_height.y * timer_height;
-                    let current_x = current_x * diff_scale_y / current_y;
-                    for fragment in text.fragments_mut() {
-                        fragment.scale = Some(PxScale {
-                            x: current_x,
-                            y: diff_scale_y,
-                        });
-                    }
-                    *timer_x = current_x;
-                    *timer_y = diff_scale_y;
-                    // width = text.width(ctx); // not really used after this
-                    width_height.y = text
-                        .measure(ctx)
-                        .expect("Should be able to get width/height of text.")
-                        .y;
-                    break;
-                }
+    /// Starts listing the pages of the booklet when the album directory
+    /// changed, or goes back to its first page for the next song.
+    fn update_booklet(&mut self) {
+        let dir = self
+            .opts
+            .booklet_slideshow
+            .then(|| self.local_song_dir())
+            .flatten();
+        match (&mut self.booklet, dir) {
+            (Some(booklet), Some(dir)) if booklet.dir == dir => booklet.restart(),
+            (_, Some(dir)) => {
+                self.booklet = Some(Booklet::start(
+                    dir,
+                    self.opts.art_names.clone(),
+                    self.opts.booklet_scans,
+                ));
             }
+            (_, None) => self.booklet = None,
+        }
+    }
 
-            *y = *offset_y - width_height.y;
-            *transform = Transform::Values {
-                dest: [TEXT_X_OFFSET, *offset_y - width_height.y].into(),
-                rotation: 0.0,
-                scale: [1.0, 1.0].into(),
-                offset: [0.0, 0.0].into(),
-            };
-
-            *offset_y -= width_height.y + TEXT_OFFSET_Y_SPACING;
+    /// Turns the page of the booklet once it was shown for "booklet-interval"
+    /// seconds, while nothing else is loading or transitioning.
+    fn advance_booklet(&mut self, ctx: &Context, delta: f32) {
+        if self.album_art.is_none() || self.art_decode.is_some() || self.art_transition.is_some() {
+            return;
+        }
+        let Some(booklet) = &mut self.booklet else {
+            return;
         };
-
-        if !self.filename_text.contents().is_empty() && !self.opts.disable_show_filename {
-            set_transform(
-                &mut self.filename_text,
-                &mut self.filename_transform,
-                &mut offset_y,
-                &mut self.cached_filename_y,
-                true,
-                false,
-                false,
-                &mut self.timer_x,
-                &mut self.timer_y,
-            );
-        } else {
+        if !booklet.advance(delta, self.opts.booklet_interval) {
+            return;
+        }
+        if let Some(page) = booklet.current_page() {
+            let page = page.to_path_buf();
             log(
-                "filename text is empty",
-                debug_log::LogState::Warning,
+                format!("Showing booklet page {}", page.display()),
+                debug_log::LogState::Debug,
                 self.opts.log_level,
             );
+            let settings = self.art_decode_settings(ctx, None);
+            self.art_decode = Some(ArtDecodeJob::start_page(page, settings, false));
+            return;
         }
-
-        if !self.album_text.contents().is_empty() && !self.opts.disable_show_album {
-            set_transform(
-                &mut self.album_text,
-                &mut self.album_transform,
-                &mut offset_y,
-                &mut self.cached_album_y,
-                true,
-                false,
-                true,
-                &mut self.timer_x,
-                &mut self.timer_y,
-            );
+        // back to the art of the song, from the source that found it
+        let Some(source) = self.opts.art_sources.get(self.art_source_idx).copied() else {
+            return;
+        };
+        match self.start_art_source(ctx, source, false) {
+            Ok(_) => {
+                if let Some(job) = &mut self.art_decode {
+                    job.is_page_turn = true;
+                }
+            }
+            Err(e) => log(e, debug_log::LogState::Warning, self.opts.log_level),
         }
+    }
 
-        if !self.artist_text.contents().is_empty() && !self.opts.disable_show_artist {
-            set_transform(
-                &mut self.artist_text,
-                &mut self.artist_transform,
-                &mut offset_y,
-                &mut self.cached_artist_y,
-                true,
-                true,
-                false,
-                &mut self.timer_x,
-                &mut self.timer_y,
-            );
-        } else {
-            log(
-                "artist text is empty",
-                debug_log::LogState::Warning,
-                self.opts.log_level,
-            );
+    /// Works through "art-sources" from the current one until a source has
+    /// album art or is still fetching it.
+    fn load_album_art(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if self.mpd_play_state == MPDPlayState::Stopped {
+            return Ok(());
         }
-
-        if !self.title_text.contents().is_empty() && !self.opts.disable_show_title {
-            set_transform(
-                &mut self.title_text,
-                &mut self.title_transform,
-                &mut offset_y,
-                &mut self.cached_title_y,
-                true,
-                false,
-                false,
-                &mut self.timer_x,
-                &mut self.timer_y,
-            );
-        } else {
+        while let Some(source) = self.opts.art_sources.get(self.art_source_idx).copied() {
+            match self.start_art_source(ctx, source, false) {
+                Ok(true) => return Ok(()),
+                Ok(false) => (),
+                Err(e) => log(e, debug_log::LogState::Warning, self.opts.log_level),
+            }
             log(
-                "title text is empty",
-                debug_log::LogState::Warning,
+                format!("No {}, trying the next art source", source.description()),
+                debug_log::LogState::Debug,
                 self.opts.log_level,
             );
+            self.art_source_idx += 1;
         }
+        self.album_art_draw_transform = None;
+        self.set_window_icon(ctx, None);
+        self.start_art_transition();
+        Ok(())
+    }
 
-        set_transform(
-            &mut self.timer_text,
-            &mut self.timer_transform,
-            &mut offset_y,
-            &mut self.cached_timer_y,
-            false,
-            false,
-            false,
-            &mut self.timer_x,
-            &mut self.timer_y,
-        );
-
-        self.update_bg_mesh(ctx)?;
+    /// Starts getting the album art from the given source. Returns false if
+    /// the source has no art for the current song.
+    fn start_art_source(
+        &mut self,
+        ctx: &Context,
+        source: ArtSource,
+        is_refresh: bool,
+    ) -> Result<bool, String> {
+        match source {
+            ArtSource::Embedded | ArtSource::MpdFolder => {
+                let (art_type, art_data) = {
+                    let read_guard = self
+                        .mpd_handler
+                        .as_ref()
+                        .map_err(|_| String::from("MPDHandler is not running"))?
+                        .get_state_read_guard()
+                        .map_err(|_| String::from("Failed to get read_guard of MPDHandlerState"))?;
+                    // the handler moves on to its next source once one fails
+                    if read_guard.get_art_source() != Some(source) {
+                        return Ok(false);
+                    }
+                    if !read_guard.is_art_data_ready() {
+                        return Ok(true);
+                    }
+                    (
+                        read_guard.get_art_type(),
+                        read_guard.get_art_data().to_vec(),
+                    )
+                };
+                log(
+                    format!("Got image_format type {art_type}"),
+                    debug_log::LogState::Debug,
+                    self.opts.log_level,
+                );
+                let settings =
+                    self.art_decode_settings(ctx, album_art::format_from_mime_type(&art_type));
+                self.art_decode = Some(ArtDecodeJob::start(art_data, source, settings, is_refresh));
+            }
+            ArtSource::LocalDir => {
+                let Some(dir) = self.local_song_dir() else {
+                    return Ok(false);
+                };
+                let settings = self.art_decode_settings(ctx, None);
+                self.art_decode = Some(ArtDecodeJob::start_local(
+                    dir,
+                    self.opts.art_names.clone(),
+                    settings,
+                    is_refresh,
+                ));
+            }
+            // generated at a fixed size, so it is never decoded again
+            ArtSource::Placeholder if is_refresh => (),
+            ArtSource::Placeholder => self.start_placeholder_art(ctx),
+        }
+        Ok(true)
+    }
 
-        Ok(())
+    fn art_decode_settings(
+        &self,
+        ctx: &Context,
+        format_hint: Option<image::ImageFormat>,
+    ) -> DecodeSettings {
+        DecodeSettings {
+            format_hint,
+            fit: self.opts.art_fit,
+            area: self.get_art_area(ctx),
+            max_size: self.opts.art_max_size,
+            backdrop: self.opts.art_backdrop.then_some((
+                self.opts.art_backdrop_blur,
+                self.opts.art_backdrop_brightness,
+            )),
+            extract_palette: self.opts.adaptive_theme,
+            animation_memory_limit: (!self.opts.disable_art_animation)
+                .then_some(self.opts.art_animation_memory as usize * 1024 * 1024),
+            icon_size: Some(window::ICON_SIZE),
+        }
     }
 
-    fn update_bg_mesh(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let filename_dimensions = self.filename_text.dimensions(ctx);
-        let album_dimensions = self.album_text.dimensions(ctx);
-        let artist_dimensions = self.artist_text.dimensions(ctx);
-        let title_dimensions = self.title_text.dimensions(ctx);
-        let timer_dimensions = self.timer_text.dimensions(ctx);
-
-        let mut mesh_builder: MeshBuilder = MeshBuilder::new();
-        if !self.opts.disable_show_filename {
-            mesh_builder.rectangle(
-                DrawMode::fill(),
-                Rect {
-                    x: TEXT_X_OFFSET,
-                    y: self.cached_filename_y,
-                    w: filename_dimensions.w,
-                    h: filename_dimensions.h,
-                },
-                Color::from_rgba(0, 0, 0, self.opts.text_bg_opacity),
-            )?;
+    /// Sets the window title from "window-title" and the current song, and
+    /// drops the album art icon once MPD stops.
+    fn update_window_info(&mut self, ctx: &Context) {
+        let title = match &self.shared {
+            Some(shared) if self.mpd_play_state != MPDPlayState::Stopped => self
+                .opts
+                .window_title
+                .render(|name| shared.get_field(name))
+                .trim()
+                .to_owned(),
+            _ => String::new(),
+        };
+        let title = if title.is_empty() {
+            String::from(window::DEFAULT_TITLE)
+        } else {
+            title
+        };
+        if title != self.window_title {
+            ctx.gfx.set_window_title(&title);
+            self.window_title = title;
         }
-        if !self.opts.disable_show_album {
-            mesh_builder.rectangle(
-                DrawMode::fill(),
-                Rect {
-                    x: TEXT_X_OFFSET,
-                    y: self.cached_album_y,
-                    w: album_dimensions.w,
-                    h: album_dimensions.h,
-                },
-                Color::from_rgba(0, 0, 0, self.opts.text_bg_opacity),
-            )?;
+        if self.mpd_play_state == MPDPlayState::Stopped {
+            self.set_window_icon(ctx, None);
         }
-        if !self.opts.disable_show_artist {
-            mesh_builder.rectangle(
-                DrawMode::fill(),
-                Rect {
-                    x: TEXT_X_OFFSET,
-                    y: self.cached_artist_y,
-                    w: artist_dimensions.w,
-                    h: artist_dimensions.h,
-                },
-                Color::from_rgba(0, 0, 0, self.opts.text_bg_opacity),
-            )?;
+    }
+
+    fn set_window_icon(&mut self, ctx: &Context, icon: Option<&image::RgbaImage>) {
+        if icon.is_none() && !self.is_window_icon_set {
+            return;
         }
-        if !self.opts.disable_show_title {
-            mesh_builder.rectangle(
-                DrawMode::fill(),
-                Rect {
-                    x: TEXT_X_OFFSET,
-                    y: self.cached_title_y,
-                    w: title_dimensions.w,
-                    h: title_dimensions.h,
-                },
-                Color::from_rgba(0, 0, 0, self.opts.text_bg_opacity),
-            )?;
+        match window::set_icon(ctx, icon) {
+            Ok(()) => self.is_window_icon_set = icon.is_some(),
+            Err(e) => log(e, debug_log::LogState::Warning, self.opts.log_level),
         }
-        if self.mpd_play_state == MPDPlayState::Playing {
-            mesh_builder.rectangle(
-                DrawMode::fill(),
-                Rect {
-                    x: TEXT_X_OFFSET,
-                    y: self.cached_timer_y,
-                    w: timer_dimensions.w,
-                    h: timer_dimensions.h,
+    }
+
+    /// Shown instead of a black screen once the album art sources before it
+    /// failed.
+    fn start_placeholder_art(&mut self, ctx: &Context) {
+        let field = |name: &str| {
+            self.shared
+                .as_ref()
+                .and_then(|shared| shared.get_field(name))
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let (album, artist) = (field("album"), field("artist"));
+        // songs without tags still get a look of their own
+        let album = if album.is_empty() && artist.is_empty() {
+            self.song_filename.clone()
+        } else {
+            album
+        };
+        log(
+            format!("Showing placeholder album art for \"{album}\" by \"{artist}\""),
+            debug_log::LogState::Debug,
+            self.opts.log_level,
+        );
+        let settings = DecodeSettings {
+            // generated at a fixed size, so there is no point in decoding it
+            // again when the window is resized
+            fit: ArtFit::Original,
+            max_size: None,
+            ..self.art_decode_settings(ctx, None)
+        };
+        self.art_decode = Some(ArtDecodeJob::start_placeholder(album, artist, settings));
+    }
+
+    /// Album art failures are shown below the notice from the MPD handler,
+    /// one per line, until the next song or until some art is shown.
+    fn add_art_notice(&mut self, notice: String) {
+        if !self.art_notice_text.contents().is_empty() {
+            self.art_notice_text.add("\n");
+        }
+        self.art_notice_text.add(notice);
+    }
+
+    /// Uploads the decoded album art and what is derived from it.
+    fn finish_art_decode(
+        &mut self,
+        ctx: &mut Context,
+        decoded: DecodedArt,
+        is_refresh: bool,
+    ) -> GameResult<()> {
+        self.album_art_backdrop = decoded.backdrop.map(|backdrop| {
+            Image::from_pixels(
+                ctx,
+                backdrop.as_raw(),
+                wgpu_types::TextureFormat::Rgba8UnormSrgb,
+                backdrop.width(),
+                backdrop.height(),
+            )
+        });
+        self.adaptive_theme = decoded.palette.map(|palette| {
+            log(
+                format!("Got album art palette {palette:?}"),
+                debug_log::LogState::Debug,
+                self.opts.log_level,
+            );
+            palette.apply_to(&self.opts.theme)
+        });
+        if decoded.is_animation_too_large {
+            log(
+                "Animated album art exceeds \"art-animation-memory\", showing its first frame only",
+                debug_log::LogState::Warning,
+                self.opts.log_level,
+            );
+        }
+        self.album_art_frames = decoded
+            .frames
+            .into_iter()
+            .map(|(frame, delay)| {
+                let image = Image::from_pixels(
+                    ctx,
+                    frame.as_raw(),
+                    wgpu_types::TextureFormat::Rgba8UnormSrgb,
+                    frame.width(),
+                    frame.height(),
+                );
+                (image, delay.as_secs_f32())
+            })
+            .collect();
+        // animated art starts on its first frame, which is already uploaded
+        self.album_art = Some(match self.album_art_frames.first() {
+            Some((image, _)) => image.clone(),
+            None => Image::from_pixels(
+                ctx,
+                decoded.image.as_raw(),
+                wgpu_types::TextureFormat::Rgba8UnormSrgb,
+                decoded.image.width(),
+                decoded.image.height(),
+            ),
+        });
+        self.art_frame_idx = 0;
+        self.art_frame_elapsed = 0.0;
+        self.is_album_art_downscaled = decoded.is_downscaled;
+        self.art_notice_text = Text::default();
+        if let Some(icon) = &decoded.icon {
+            self.set_window_icon(ctx, Some(icon));
+        }
+
+        self.get_album_art_transform(ctx);
+        if !is_refresh {
+            self.start_art_transition();
+        }
+        if self.adaptive_theme.is_some() {
+            self.update_bg_mesh(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Picks up the album art once the worker thread is done with it, and
+    /// decodes downscaled art again once the window size settles.
+    fn poll_art_decode(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(job) = &self.art_decode {
+            let Some(result) = job.poll() else {
+                return Ok(());
+            };
+            let is_refresh = job.is_refresh;
+            let is_page_turn = job.is_page_turn;
+            let source = job.source;
+            let page = job.page.clone();
+            self.art_decode = None;
+            match (result, page) {
+                (Ok(decoded), _) if is_page_turn && !is_refresh => {
+                    // pages always crossfade, whatever "transition" is
+                    self.take_old_album_art();
+                    self.art_transition =
+                        Transition::start(TransitionKind::Crossfade, self.opts.transition_time);
+                    self.finish_art_decode(ctx, decoded, false)?;
+                }
+                (Ok(decoded), _) => self.finish_art_decode(ctx, decoded, is_refresh)?,
+                (Err(e), _) if is_refresh => {
+                    log(e, debug_log::LogState::Debug, self.opts.log_level);
+                }
+                (Err(e), Some(page)) => {
+                    log(
+                        format!("Failed to show booklet page {}: {e}", page.display()),
+                        debug_log::LogState::Warning,
+                        self.opts.log_level,
+                    );
+                    if let Some(booklet) = &mut self.booklet {
+                        booklet.remove_page(&page);
+                    }
+                }
+                (Err(e), None) => {
+                    let notice = format!("Failed to show the {}: {e}", source.description());
+                    log(&notice, debug_log::LogState::Warning, self.opts.log_level);
+                    self.add_art_notice(notice);
+                    if let Ok(handler) = &self.mpd_handler
+                        && handler.skip_art_source(source).is_err()
+                    {
+                        log(
+                            "Failed to skip the album art source of the MPDHandler",
+                            debug_log::LogState::Warning,
+                            self.opts.log_level,
+                        );
+                    }
+                    self.art_source_idx += 1;
+                    self.load_album_art(ctx)?;
+                }
+            }
+        } else if self.is_art_redecode_needed
+            && self.album_art.is_some()
+            && self.resize_instant.elapsed() >= ART_REDECODE_DELAY
+        {
+            self.is_art_redecode_needed = false;
+            if let Some(page) = self
+                .booklet
+                .as_ref()
+                .and_then(|booklet| booklet.current_page())
+            {
+                let settings = self.art_decode_settings(ctx, None);
+                self.art_decode =
+                    Some(ArtDecodeJob::start_page(page.to_path_buf(), settings, true));
+            } else if let Some(source) = self.opts.art_sources.get(self.art_source_idx).copied()
+                && let Err(e) = self.start_art_source(ctx, source, true)
+            {
+                log(e, debug_log::LogState::Debug, self.opts.log_level);
+            }
+        }
+        Ok(())
+    }
+
+    fn row_text(&self, row: TextRow) -> &Text {
+        match row {
+            TextRow::Title => &self.title_text,
+            TextRow::Artist => &self.artist_text,
+            TextRow::Album => &self.album_text,
+            TextRow::Filename => &self.filename_text,
+            TextRow::Timer => &self.timer_text,
+        }
+    }
+
+    fn row_string_cache_mut(&mut self, row: TextRow) -> &mut String {
+        match row {
+            TextRow::Title => &mut self.title_string_cache,
+            TextRow::Artist => &mut self.artist_string_cache,
+            TextRow::Album => &mut self.album_string_cache,
+            TextRow::Filename => &mut self.filename_string_cache,
+            TextRow::Timer => unreachable!("The timer text is not cached"),
+        }
+    }
+
+    fn row_transform(&self, row: TextRow) -> Transform {
+        match row {
+            TextRow::Title => self.title_transform,
+            TextRow::Artist => self.artist_transform,
+            TextRow::Album => self.album_transform,
+            TextRow::Filename => self.filename_transform,
+            TextRow::Timer => self.timer_transform,
+        }
+    }
+
+    fn row_text_and_transform_mut(&mut self, row: TextRow) -> (&mut Text, &mut Transform) {
+        match row {
+            TextRow::Title => (&mut self.title_text, &mut self.title_transform),
+            TextRow::Artist => (&mut self.artist_text, &mut self.artist_transform),
+            TextRow::Album => (&mut self.album_text, &mut self.album_transform),
+            TextRow::Filename => (&mut self.filename_text, &mut self.filename_transform),
+            TextRow::Timer => (&mut self.timer_text, &mut self.timer_transform),
+        }
+    }
+
+    /// Whether the row takes up space in the layout. The timer always does
+    /// if it is part of the layout, even while it is not drawn.
+    fn is_row_shown(&self, row: TextRow) -> bool {
+        let is_disabled = match row {
+            TextRow::Title => self.opts.disable_show_title,
+            TextRow::Artist => self.opts.disable_show_artist,
+            TextRow::Album => self.opts.disable_show_album,
+            TextRow::Filename => self.opts.disable_show_filename,
+            TextRow::Timer => return self.opts.layout.order.contains(&TextRow::Timer),
+        };
+        !is_disabled && !self.row_text(row).contents().is_empty()
+    }
+
+    /// The song dependent rows that are shown, from top to bottom.
+    fn shown_song_rows(&self) -> Vec<TextRow> {
+        self.opts
+            .layout
+            .order
+            .iter()
+            .copied()
+            .filter(|row| *row != TextRow::Timer && self.is_row_shown(*row))
+            .collect()
+    }
+
+    fn get_aligned_x(&self, ctx: &Context, width: f32) -> f32 {
+        let area = self.get_text_area(ctx);
+        let margin = self.opts.layout.margin;
+        if width > area.w - margin * 2.0 {
+            // too wide to be aligned, like scrolling marquee text
+            return area.x + margin;
+        }
+        match self.opts.layout.align {
+            HorizontalAlign::Left => area.x + margin,
+            HorizontalAlign::Center => area.x + (area.w - width) / 2.0,
+            HorizontalAlign::Right => area.x + area.w - margin - width,
+        }
+    }
+
+    /// Moves the timer to its aligned position, as its width changes while
+    /// playing.
+    fn align_timer(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let x = self.get_aligned_x(ctx, self.timer_text.dimensions(ctx).w);
+        if let Transform::Values { dest, .. } = &mut self.timer_transform
+            && dest.x != x
+        {
+            dest.x = x;
+            self.update_bg_mesh(ctx)?;
+        }
+        Ok(())
+    }
+
+    fn refresh_text_transforms(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let drawable_height = self.screen_size(ctx).1.abs();
+        let text_width = self.get_text_area(ctx).w;
+
+        let margin = self.opts.layout.margin;
+        let row_spacing = self.opts.layout.row_spacing;
+        let is_marquee = self.opts.text_overflow == TextOverflow::Marquee;
+        let wrap_lines = if is_marquee { 1 } else { self.opts.wrap_lines };
+        let wrap_width = text_width - margin;
+
+        // Sets the font size of the text to fit the given height limit and the
+        // width of the text area, returning the resulting size and font size.
+        let fit_text = |text: &mut Text, is_string: bool, height_limit: f32| {
+            let mut current_x = INIT_FONT_SIZE_X;
+            let mut current_y = INIT_FONT_SIZE_Y;
+            let mut width_height: Vector2<f32> = Vector2 { x: 0.0, y: 0.0 };
+            let mut iteration_count: u8 = 0;
+            loop {
+                iteration_count += 1;
+                if iteration_count > 8 {
+                    break;
+                }
+
+                for fragment in text.fragments_mut() {
+                    fragment.scale = Some(PxScale {
+                        x: current_x,
+                        y: current_y,
+                    });
+                }
+                text.set_bounds([f32::INFINITY, f32::INFINITY]);
+                width_height = text
+                    .measure(ctx)
+                    .expect("Should be able to get width/height of text.");
+
+                if is_string && wrap_lines > 1 {
+                    // glyph_brush breaks lines following the unicode line
+                    // breaking rules, so CJK text wraps between characters
+                    let single_line = width_height;
+                    text.set_bounds([wrap_width, f32::INFINITY]);
+                    width_height = text
+                        .measure(ctx)
+                        .expect("Should be able to get width/height of text.");
+                    let line_count = (width_height.y / single_line.y).round().max(1.0) as u32;
+                    if line_count > wrap_lines || single_line.y >= height_limit {
+                        current_x *= DECREASE_AMT;
+                        current_y *= DECREASE_AMT;
+                        continue;
+                    } else if line_count == 1 && text_width * MIN_WIDTH_RATIO > single_line.x {
+                        current_x *= INCREASE_AMT;
+                        current_y *= INCREASE_AMT;
+                        continue;
+                    } else {
+                        break;
+                    }
+                } else if is_string && !is_marquee {
+                    if text_width < width_height.x || width_height.y >= height_limit {
+                        current_x *= DECREASE_AMT;
+                        current_y *= DECREASE_AMT;
+                        continue;
+                    } else if text_width * MIN_WIDTH_RATIO > width_height.x {
+                        current_x *= INCREASE_AMT;
+                        current_y *= INCREASE_AMT;
+                        continue;
+                    } else {
+                        break;
+                    }
+                } else {
+                    // The timer, and in marquee mode every row, is sized to
+                    // the height limit regardless of its width
+                    let diff_scale_y = current_y / width_height.y * height_limit;
+                    current_x = current_x * diff_scale_y / current_y;
+                    current_y = diff_scale_y;
+                    for fragment in text.fragments_mut() {
+                        fragment.scale = Some(PxScale {
+                            x: current_x,
+                            y: current_y,
+                        });
+                    }
+                    width_height = text
+                        .measure(ctx)
+                        .expect("Should be able to get width/height of text.");
+                    break;
+                }
+            }
+
+            (
+                width_height,
+                PxScale {
+                    x: current_x,
+                    y: current_y,
                 },
-                Color::from_rgba(0, 0, 0, self.opts.text_bg_opacity),
-            )?;
+            )
+        };
+
+        let mut sizes: Vec<(TextRow, Vector2<f32>)> = Vec::new();
+        for row in self.opts.layout.order.clone() {
+            if !self.is_row_shown(row) {
+                log(
+                    format!("{row:?} row is not shown"),
+                    debug_log::LogState::Debug,
+                    self.opts.log_level,
+                );
+                continue;
+            }
+            let height_limit = self
+                .opts
+                .layout
+                .height_ratio(row, self.opts.force_text_height_scale)
+                * drawable_height;
+            let (text, _) = self.row_text_and_transform_mut(row);
+            let (size, scale) = fit_text(text, row != TextRow::Timer, height_limit);
+            if row == TextRow::Timer {
+                self.timer_x = scale.x;
+                self.timer_y = scale.y;
+            }
+            sizes.push((row, size));
         }
-        let mesh: Mesh = Mesh::from_data(ctx, mesh_builder.build());
 
-        self.text_bg_mesh = Some(mesh);
+        let total_height = sizes.iter().map(|(_, size)| size.y).sum::<f32>()
+            + row_spacing * sizes.len().saturating_sub(1) as f32;
+        let vertical_margin = self.opts.layout.vertical_margin;
+        let mut offset_y = match self.opts.layout.anchor {
+            VerticalAnchor::Top => vertical_margin,
+            VerticalAnchor::Middle => (drawable_height - total_height) / 2.0,
+            VerticalAnchor::Bottom => drawable_height - vertical_margin - total_height,
+        };
+        for (row, size) in sizes {
+            let x = self.get_aligned_x(ctx, size.x);
+            let (_, transform) = self.row_text_and_transform_mut(row);
+            *transform = Transform::Values {
+                dest: [x, offset_y].into(),
+                rotation: 0.0,
+                scale: [1.0, 1.0].into(),
+                offset: [0.0, 0.0].into(),
+            };
+            offset_y += size.y + row_spacing;
+        }
+
+        self.is_marquee_scrolling = is_marquee
+            && self
+                .shown_song_rows()
+                .into_iter()
+                .any(|row| self.get_marquee_overflow(ctx, self.row_text(row)) > 0.0);
+
+        self.update_bg_mesh(ctx)
+    }
+
+    /// How much wider than the text area the text is in marquee mode.
+    fn get_marquee_overflow(&self, ctx: &Context, text: &Text) -> f32 {
+        if self.opts.text_overflow != TextOverflow::Marquee {
+            return 0.0;
+        }
+        let available_width = self.get_text_area(ctx).w - self.opts.layout.margin * 2.0;
+        text.dimensions(ctx).w - available_width
+    }
+
+    fn get_marquee_offset(&self, ctx: &Context, text: &Text) -> f32 {
+        marquee_offset(
+            self.get_marquee_overflow(ctx, text),
+            self.marquee_elapsed,
+            self.opts.marquee_speed,
+            self.opts.marquee_pause,
+        )
+    }
+
+    fn update_bg_mesh(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.is_bg_mesh_adaptive = self.is_theme_adaptive();
+        if self.get_split_side(ctx).is_some() {
+            // the text does not cover the album art when split
+            self.text_bg_mesh = None;
+            self.timer_bg_mesh = None;
+            return Ok(());
+        }
+
+        let theme = self.theme();
+        let drawable_width = self.screen_size(ctx).0.abs();
+        let get_row_rect = |row: TextRow| {
+            let padding = theme.row(row).text_bg_padding;
+            let dimensions = self.row_text(row).dimensions(ctx);
+            let (x, y) = transform_dest(&self.row_transform(row));
+            let x = x - padding;
+            Rect {
+                x,
+                y: y - padding,
+                // scrolling text may be wider than the window
+                w: (dimensions.w + padding * 2.0).min(drawable_width - x),
+                h: dimensions.h + padding * 2.0,
+            }
+        };
+
+        let rows = self.shown_song_rows();
+        let mesh = if rows.is_empty() {
+            None
+        } else {
+            let mut mesh_builder: MeshBuilder = MeshBuilder::new();
+            for row in rows {
+                mesh_builder.rectangle(
+                    DrawMode::fill(),
+                    get_row_rect(row),
+                    theme.row(row).text_bg_color,
+                )?;
+            }
+            Some(Mesh::from_data(ctx, mesh_builder.build()))
+        };
+        // the timer is not part of the song transitions, so it gets its own mesh
+        let timer_mesh =
+            if self.mpd_play_state == MPDPlayState::Playing && self.is_row_shown(TextRow::Timer) {
+                Some(Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    get_row_rect(TextRow::Timer),
+                    theme.timer.text_bg_color,
+                )?)
+            } else {
+                None
+            };
+
+        self.text_bg_mesh = mesh;
+        self.timer_bg_mesh = timer_mesh;
 
         Ok(())
     }
 
+    /// The idle screen replaces the black window while MPD is stopped, and
+    /// the paused song after "idle-after-pause" seconds.
+    fn is_idle(&self) -> bool {
+        match self.mpd_play_state {
+            MPDPlayState::Stopped => self.opts.idle_screen && self.is_initialized,
+            MPDPlayState::Paused => self
+                .opts
+                .idle_after_pause
+                .zip(self.paused_instant)
+                .is_some_and(|(secs, instant)| instant.elapsed().as_secs_f32() >= secs),
+            MPDPlayState::Playing => false,
+        }
+    }
+
+    fn idle_stats_string(stats: MPDStats) -> String {
+        format!(
+            "{} songs, {} albums, {} artists, up {}",
+            stats.songs,
+            stats.albums,
+            stats.artists,
+            clock::format_uptime(stats.uptime)
+        )
+    }
+
+    /// Rebuilds the rows of the idle screen when the clock ticks over or the
+    /// window is resized, centering them in the window.
+    fn refresh_idle_screen(&mut self, ctx: &mut Context) {
+        let now = LocalTime::now();
+        let stats = self
+            .shared
+            .as_ref()
+            .and_then(|shared| shared.stats)
+            .filter(|_| self.opts.idle_stats);
+        let strings = vec![
+            now.format(&self.opts.clock_format),
+            now.format(&self.opts.date_format),
+            self.last_song.clone(),
+            stats.map(Self::idle_stats_string).unwrap_or_default(),
+        ];
+        let (width, height) = self.screen_size(ctx);
+        let (width, height) = (width.abs(), height.abs());
+        if strings == self.idle_strings && (width, height) == self.idle_size {
+            return;
+        }
+
+        let text_color = self.opts.theme.default.text_color;
+        let mut rows = Vec::new();
+        for (idx, string) in strings.iter().enumerate() {
+            if string.is_empty() {
+                continue;
+            }
+            let mut text = string_to_text(string.clone(), None, &mut self.loaded_fonts, ctx);
+            let mut scale = height * IDLE_ROW_HEIGHT_RATIOS[idx];
+            text.set_scale(scale);
+            let text_width = text.dimensions(ctx).w;
+            if text_width > width * 0.9 {
+                scale *= width * 0.9 / text_width;
+                text.set_scale(scale);
+            }
+            // the clock and date stand out, the rest is dimmed
+            let color = if idx < 2 {
+                text_color
+            } else {
+                fade(text_color, IDLE_DIM_ALPHA)
+            };
+            rows.push((text, color));
+        }
+        let spacing = height * IDLE_ROW_SPACING_RATIO;
+        let total_height = rows
+            .iter()
+            .map(|(text, _)| text.dimensions(ctx).h)
+            .sum::<f32>()
+            + spacing * rows.len().saturating_sub(1) as f32;
+        let mut y = (height - total_height) / 2.0;
+        self.idle_rows = rows
+            .into_iter()
+            .map(|(text, color)| {
+                let dimensions = text.dimensions(ctx);
+                let dest = [(width - dimensions.w) / 2.0, y];
+                y += dimensions.h + spacing;
+                (text, dest, color)
+            })
+            .collect();
+        self.idle_strings = strings;
+        self.idle_size = (width, height);
+    }
+
+    /// Darkens the window once MPD has not been playing for "dim-after"
+    /// seconds.
+    fn draw_dim_overlay(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult<()> {
+        if self.mpd_play_state == MPDPlayState::Playing {
+            return Ok(());
+        }
+        let alpha = burn_in::dim_alpha(
+            self.play_state_instant.elapsed().as_secs_f32(),
+            self.opts.dim_after,
+            self.opts.dim_brightness,
+        );
+        if alpha <= 0.0 {
+            return Ok(());
+        }
+        let (width, height) = self.screen_size(ctx);
+        let overlay = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, width.abs(), height.abs()),
+            fade(Color::BLACK, alpha),
+        )?;
+        canvas.draw(&overlay, DrawParam::default());
+        Ok(())
+    }
+
+    /// The colors picked from the album art are only used while the art is
+    /// shown.
+    fn is_theme_adaptive(&self) -> bool {
+        self.adaptive_theme.is_some() && (self.album_art.is_some() || self.old_album_art.is_some())
+    }
+
+    fn theme(&self) -> &Theme {
+        match &self.adaptive_theme {
+            Some(adaptive_theme) if self.is_theme_adaptive() => adaptive_theme,
+            _ => &self.opts.theme,
+        }
+    }
+
+    /// Keeps the current album art on screen until the next one is decoded.
+    fn take_old_album_art(&mut self) {
+        // a pending decode is for the art being replaced
+        self.art_decode = None;
+        self.album_art_frames.clear();
+        if let Some(image) = self.album_art.take()
+            && let Some(transform) = self.album_art_draw_transform.take()
+        {
+            self.old_album_art = Some(OldAlbumArt {
+                image,
+                transform,
+                backdrop: self
+                    .album_art_backdrop
+                    .take()
+                    .zip(self.album_art_backdrop_transform.take()),
+            });
+            self.art_transition = None;
+        }
+    }
+
+    /// Called once the new album art is decoded, or once it is known that
+    /// there is none.
+    fn start_art_transition(&mut self) {
+        if self.old_album_art.is_some() && self.art_transition.is_none() {
+            self.art_transition =
+                Transition::start(self.opts.transition, self.opts.transition_time);
+            if self.art_transition.is_none() {
+                self.old_album_art = None;
+            }
+        }
+    }
+
+    fn snapshot_text(&self) -> OldText {
+        let theme = self.theme();
+        OldText {
+            rows: self
+                .shown_song_rows()
+                .into_iter()
+                .map(|row| {
+                    (
+                        self.row_text(row).clone(),
+                        self.row_transform(row),
+                        theme.row(row).text_color,
+                    )
+                })
+                .collect(),
+            bg_mesh: self.text_bg_mesh.clone(),
+        }
+    }
+
+    fn can_send_commands(&self) -> bool {
+        self.is_valid && self.is_initialized && self.mpd_handler.is_ok()
+    }
+
+    fn can_use_mouse(&self) -> bool {
+        !self.opts.disable_mouse_control && self.can_send_commands()
+    }
+
+    fn send_command(&self, command: MPDCommand) {
+        log(
+            format!("Sending command {command:?}"),
+            debug_log::LogState::Debug,
+            self.opts.log_level,
+        );
+        if let Ok(mpd_handler) = &self.mpd_handler {
+            mpd_handler.queue_command(command);
+        }
+    }
+
+    fn get_progress_bar_height(&self, ctx: &Context) -> f32 {
+        self.timer_text.dimensions(ctx).h * PROGRESS_BAR_HEIGHT_RATIO
+    }
+
+    /// The band covering the timer row and the progress bar above it, as wide
+    /// as the text area.
+    fn get_progress_area(&self, ctx: &Context) -> Option<Rect> {
+        if self.mpd_play_state == MPDPlayState::Stopped
+            || self.length <= 0.0
+            || !self.is_row_shown(TextRow::Timer)
+        {
+            return None;
+        }
+        let area = self.get_text_area(ctx);
+        let bar_height = self.get_progress_bar_height(ctx);
+        Some(Rect {
+            x: area.x,
+            y: transform_dest(&self.timer_transform).1 - bar_height,
+            w: area.w,
+            h: self.timer_text.dimensions(ctx).h + bar_height,
+        })
+    }
+
+    fn seek_to_x(&mut self, ctx: &Context, x: f32, force_send: bool) {
+        let area = self.get_text_area(ctx);
+        if area.w <= 0.0 {
+            return;
+        }
+        let ratio = ((x - area.x) / area.w).clamp(0.0, 1.0) as f64;
+        self.timer = self.length * ratio;
+        if force_send || self.seek_send_instant.elapsed() > SEEK_SEND_INTERVAL {
+            self.seek_send_instant = Instant::now();
+            self.send_command(MPDCommand::SeekCur(self.timer));
+        }
+    }
+
+    fn toggle_pause(&self) {
+        match self.mpd_play_state {
+            MPDPlayState::Playing => self.send_command(MPDCommand::Pause(true)),
+            MPDPlayState::Paused => self.send_command(MPDCommand::Pause(false)),
+            MPDPlayState::Stopped => self.send_command(MPDCommand::Play),
+        }
+    }
+
+    fn change_volume(&mut self, amount: i32) -> bool {
+        if self.volume < 0 {
+            // MPD has no mixer or has not reported the volume yet
+            return false;
+        }
+        let new_volume = (self.volume + amount).clamp(0, 100);
+        if new_volume != self.volume {
+            self.volume = new_volume;
+            self.send_command(MPDCommand::SetVolume(new_volume as u8));
+        }
+        true
+    }
+
+    fn show_gesture_ack(&mut self, ctx: &Context, message: String) {
+        let height = self.screen_size(ctx).1.abs() * GESTURE_ACK_HEIGHT_SCALE;
+        self.gesture_ack_text = Text::new(TextFragment::new(message).scale(PxScale::from(height)));
+        self.gesture_ack_remaining = GESTURE_ACK_DURATION;
+    }
+
+    fn handle_gesture(&mut self, ctx: &Context, gesture: Gesture) {
+        log(
+            format!("Got gesture {gesture:?}"),
+            debug_log::LogState::Debug,
+            self.opts.log_level,
+        );
+        let message = match gesture {
+            Gesture::Tap => {
+                self.toggle_pause();
+                if self.mpd_play_state == MPDPlayState::Playing {
+                    "Pause".to_owned()
+                } else {
+                    "Play".to_owned()
+                }
+            }
+            Gesture::LongPress => {
+                self.hide_text = !self.hide_text;
+                if self.hide_text {
+                    "Text hidden".to_owned()
+                } else {
+                    "Text shown".to_owned()
+                }
+            }
+            Gesture::SwipeLeft => {
+                self.send_command(MPDCommand::Next);
+                "Next".to_owned()
+            }
+            Gesture::SwipeRight => {
+                self.send_command(MPDCommand::Previous);
+                "Previous".to_owned()
+            }
+            Gesture::TwoFingerSwipeUp | Gesture::TwoFingerSwipeDown => {
+                let amount = if gesture == Gesture::TwoFingerSwipeUp {
+                    TOUCH_VOLUME_STEP
+                } else {
+                    -TOUCH_VOLUME_STEP
+                };
+                if self.change_volume(amount) {
+                    format!("Volume {}%", self.volume)
+                } else {
+                    "Volume unavailable".to_owned()
+                }
+            }
+        };
+        self.show_gesture_ack(ctx, message);
+    }
+
     #[allow(dead_code)]
     pub fn is_authenticated(&self) -> bool {
         self.is_authenticated
@@ -839,6 +1976,8 @@ impl EventHandler for MPDDisplay {
             self.close_request_handled = true;
         }
 
+        self.check_config_reload(ctx)?;
+
         self.prev_mpd_play_state = self.mpd_play_state;
 
         if self.is_valid && self.is_initialized && self.poll_instant.elapsed() > POLL_TIME {
@@ -861,7 +2000,9 @@ impl EventHandler for MPDDisplay {
                     .unwrap()
                     .get_mpd_handler_shared_state()
                     .ok();
-                if let Some(shared) = &self.shared {
+                let mut is_song_changed = false;
+                if let Some(shared) = self.shared.clone() {
+                    self.volume = shared.volume;
                     if self.notice_text.contents() != shared.error_text {
                         self.notice_text = Text::new(TextFragment::new(shared.error_text.clone()));
                     }
@@ -874,84 +2015,99 @@ impl EventHandler for MPDDisplay {
                             self.timer = 0.0;
                             self.length = 0.0;
                             self.album_art = None;
+                            self.album_art_frames.clear();
+                            self.old_album_art = None;
+                            self.art_transition = None;
+                            self.art_decode = None;
+                            self.art_source_idx = 0;
+                            self.booklet = None;
+                            self.art_notice_text = Text::default();
+                            self.old_text = None;
+                            self.text_transition = None;
+                            self.song_filename.clear();
                             self.filename_string_cache.clear();
                             self.artist_string_cache.clear();
                             self.title_string_cache.clear();
                             self.album_string_cache.clear();
                         }
+                        if shared.mpd_play_state != MPDPlayState::Paused {
+                            self.paused_instant = None;
+                        } else if self.paused_instant.is_none() {
+                            self.paused_instant = Some(Instant::now());
+                        }
                         self.mpd_play_state = shared.mpd_play_state;
                     } else {
                         self.mpd_play_state = MPDPlayState::Playing;
-                        if !shared.title.is_empty() {
-                            if shared.title != self.title_string_cache {
-                                self.title_string_cache = shared.title.clone();
-                                self.title_text = string_to_text(
-                                    shared.title.clone(),
+                        self.paused_instant = None;
+                        self.last_song = [&shared.title, &shared.artist]
+                            .into_iter()
+                            .filter(|field| !field.is_empty())
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .join(" - ");
+                        if self.last_song.is_empty() {
+                            self.last_song = shared.filename.clone();
+                        }
+                        is_song_changed =
+                            !shared.filename.is_empty() && self.song_filename != shared.filename;
+                        if is_song_changed {
+                            self.marquee_elapsed = 0.0;
+                            self.text_transition =
+                                Transition::start(self.opts.transition, self.opts.transition_time);
+                            self.old_text = self.text_transition.map(|_| self.snapshot_text());
+                        }
+                        if is_song_changed {
+                            self.song_filename = shared.filename.clone();
+                            self.art_source_idx = 0;
+                            self.art_notice_text = Text::default();
+                        }
+                        for row in [
+                            TextRow::Title,
+                            TextRow::Artist,
+                            TextRow::Album,
+                            TextRow::Filename,
+                        ] {
+                            let rendered = self
+                                .opts
+                                .templates
+                                .row(row)
+                                .map(|template| template.render(|name| shared.get_field(name)))
+                                .unwrap_or_default();
+                            if rendered.is_empty() {
+                                if is_song_changed {
+                                    self.row_string_cache_mut(row).clear();
+                                    *self.row_text_and_transform_mut(row).0 = Text::default();
+                                }
+                                // the tags may not have been received yet
+                                self.dirty_flag
+                                    .as_ref()
+                                    .unwrap()
+                                    .store(true, Ordering::Release);
+                            } else if rendered != *self.row_string_cache_mut(row) {
+                                let font = get_custom_font(
+                                    self.opts.theme.row(row).font.as_ref(),
+                                    &mut self.custom_fonts,
+                                    ctx,
+                                    self.opts.log_level,
+                                );
+                                let text = string_to_text(
+                                    rendered.clone(),
+                                    font.as_ref(),
                                     &mut self.loaded_fonts,
                                     ctx,
                                 );
+                                *self.row_string_cache_mut(row) = rendered;
+                                *self.row_text_and_transform_mut(row).0 = text;
                                 log(
                                     format!("loaded_fonts size is {}", self.loaded_fonts.len()),
                                     debug_log::LogState::Debug,
                                     self.opts.log_level,
                                 );
                             }
-                        } else {
-                            self.dirty_flag
-                                .as_ref()
-                                .unwrap()
-                                .store(true, Ordering::Release);
                         }
-                        if !shared.artist.is_empty() {
-                            if shared.artist != self.artist_string_cache {
-                                self.artist_string_cache = shared.artist.clone();
-                                self.artist_text = string_to_text(
-                                    shared.artist.clone(),
-                                    &mut self.loaded_fonts,
-                                    ctx,
-                                );
-                            }
-                        } else {
-                            self.dirty_flag
-                                .as_ref()
-                                .unwrap()
-                                .store(true, Ordering::Release);
+                        if !self.is_seeking {
+                            self.timer = shared.pos;
                         }
-                        if !shared.album.is_empty() {
-                            if shared.album != self.album_string_cache {
-                                self.album_string_cache = shared.album.clone();
-                                self.album_text = string_to_text(
-                                    shared.album.clone(),
-                                    &mut self.loaded_fonts,
-                                    ctx,
-                                );
-                            }
-                        } else {
-                            self.dirty_flag
-                                .as_ref()
-                                .unwrap()
-                                .store(true, Ordering::Release);
-                        }
-                        if !shared.filename.is_empty() {
-                            if shared.filename != self.filename_string_cache {
-                                self.filename_string_cache = shared.filename.clone();
-                                if self.filename_text.contents() != shared.filename {
-                                    self.album_art = None;
-                                    self.tried_album_art_in_dir = false;
-                                }
-                                self.filename_text = string_to_text(
-                                    shared.filename.clone(),
-                                    &mut self.loaded_fonts,
-                                    ctx,
-                                );
-                            }
-                        } else {
-                            self.dirty_flag
-                                .as_ref()
-                                .unwrap()
-                                .store(true, Ordering::Release);
-                        }
-                        self.timer = shared.pos;
                         self.length = shared.length;
                         self.refresh_text_transforms(ctx)?;
                     }
@@ -962,20 +2118,83 @@ impl EventHandler for MPDDisplay {
                         self.opts.log_level,
                     );
                 }
-                if self.album_art.is_none() {
-                    let result = self.get_image_from_data(ctx);
-                    if let Err(e) = result {
-                        log(e, debug_log::LogState::Warning, self.opts.log_level);
-                        self.album_art = None;
-                        self.album_art_draw_transform = None;
-                    } else {
-                        self.get_album_art_transform(ctx, !self.opts.do_not_fill_scale_album_art);
-                    }
+                self.update_window_info(ctx);
+                if is_song_changed {
+                    self.take_old_album_art();
+                    self.update_booklet();
+                }
+                if self.album_art.is_none() && self.art_decode.is_none() {
+                    self.load_album_art(ctx)?;
                 }
             }
         }
 
+        if (self.mpd_play_state == MPDPlayState::Playing)
+            != (self.prev_mpd_play_state == MPDPlayState::Playing)
+        {
+            self.play_state_instant = Instant::now();
+        }
+
+        self.poll_art_decode(ctx)?;
+
+        if self.is_idle() {
+            self.refresh_idle_screen(ctx);
+        }
+
+        if self.is_theme_adaptive() != self.is_bg_mesh_adaptive {
+            self.update_bg_mesh(ctx)?;
+        }
+
+        if let Some(gesture) = self.gesture_recognizer.poll(Instant::now()) {
+            self.handle_gesture(ctx, gesture);
+        }
+
         let delta = ctx.time.delta();
+        if self.mpd_play_state != MPDPlayState::Stopped {
+            self.advance_booklet(ctx, delta.as_secs_f32());
+        }
+        // The first frame after the low fps mode includes the time slept, so
+        // it is left out of the animations instead of making them skip ahead
+        let anim_delta = if self.is_low_fps {
+            0.0
+        } else {
+            delta.as_secs_f32()
+        };
+        if self.gesture_ack_remaining > 0.0 {
+            self.gesture_ack_remaining -= anim_delta;
+            self.resize_ticks = 0;
+        }
+        if self.is_marquee_scrolling {
+            self.marquee_elapsed += anim_delta;
+            self.resize_ticks = 0;
+        }
+        if let Some(transition) = &mut self.art_transition {
+            if !transition.advance(anim_delta) {
+                self.art_transition = None;
+                self.old_album_art = None;
+            }
+            self.resize_ticks = 0;
+        }
+        if !self.album_art_frames.is_empty() {
+            self.art_frame_elapsed += anim_delta;
+            let mut is_frame_changed = false;
+            while self.art_frame_elapsed >= self.album_art_frames[self.art_frame_idx].1 {
+                self.art_frame_elapsed -= self.album_art_frames[self.art_frame_idx].1;
+                self.art_frame_idx = (self.art_frame_idx + 1) % self.album_art_frames.len();
+                is_frame_changed = true;
+            }
+            if is_frame_changed {
+                self.album_art = Some(self.album_art_frames[self.art_frame_idx].0.clone());
+            }
+            self.resize_ticks = 0;
+        }
+        if let Some(transition) = &mut self.text_transition {
+            if !transition.advance(anim_delta) {
+                self.text_transition = None;
+                self.old_text = None;
+            }
+            self.resize_ticks = 0;
+        }
         self.timer += delta.as_secs_f64();
         let mut timer_diff = seconds_to_time(self.length - self.timer);
         if !self.opts.disable_show_percentage {
@@ -983,10 +2202,19 @@ impl EventHandler for MPDDisplay {
         }
         let timer_diff_len = timer_diff.len();
         self.timer_text = Text::new(timer_diff);
+        if let Some((_, font_name)) = get_custom_font(
+            self.opts.theme.timer.font.as_ref(),
+            &mut self.custom_fonts,
+            ctx,
+            self.opts.log_level,
+        ) {
+            self.timer_text.set_font(font_name);
+        }
         self.timer_text.set_scale(PxScale {
             x: self.timer_x,
             y: self.timer_y,
         });
+        self.align_timer(ctx)?;
         if timer_diff_len != self.timer_text_len {
             self.timer_text_len = timer_diff_len;
             self.update_bg_mesh(ctx)?;
@@ -996,94 +2224,308 @@ impl EventHandler for MPDDisplay {
             self.update_bg_mesh(ctx)?;
         }
 
-        if self.resize_ticks < RESIZE_TICKS_MAX {
-            self.resize_ticks += 1;
-        } else {
+        self.is_low_fps = self.resize_ticks >= RESIZE_TICKS_MAX;
+        if self.is_low_fps {
             // sleep to force ~5 fps
             thread::sleep(Duration::from_millis(200));
+        } else {
+            self.resize_ticks += 1;
         }
 
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> Result<(), GameError> {
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        let elapsed = ctx.time.time_since_start().as_secs_f32();
+        let art_shift = burn_in::pixel_shift_offset(
+            elapsed,
+            self.opts.pixel_shift,
+            self.opts.pixel_shift_interval,
+        );
+        let drift = burn_in::drift_offset(elapsed, self.opts.text_drift);
+        let text_shift = [art_shift[0] + drift[0], art_shift[1] + drift[1]];
+
+        if self.is_idle() {
+            let mut canvas = self.scene_canvas(ctx, self.opts.theme.background_color);
+            for (text, dest, color) in &self.idle_rows {
+                canvas.draw(
+                    text,
+                    burn_in_param(DrawParam::default().dest(*dest).color(*color), text_shift),
+                );
+            }
+            if !self.hide_text {
+                canvas.draw(
+                    &self.notice_text,
+                    burn_in_param(
+                        DrawParam::default().color(self.opts.theme.default.text_color),
+                        text_shift,
+                    ),
+                );
+            }
+            self.draw_dim_overlay(ctx, &mut canvas)?;
+            return self.finish_scene(ctx, canvas);
+        }
 
-        if self.mpd_play_state != MPDPlayState::Stopped
-            && let Some(album_art) = &self.album_art
-            && let Some(draw_transform) = &self.album_art_draw_transform
-        {
-            canvas.draw(
-                album_art,
-                DrawParam {
-                    transform: *draw_transform,
-                    ..Default::default()
-                },
-            );
+        let mut canvas = self.scene_canvas(ctx, self.theme().background_color);
+
+        let window_width = self.screen_size(ctx).0.abs();
+        let art_frame = self
+            .art_transition
+            .map_or(TransitionFrame::DONE, |transition| transition.frame());
+        let text_frame = self
+            .text_transition
+            .map_or(TransitionFrame::DONE, |transition| transition.frame());
+
+        if self.mpd_play_state != MPDPlayState::Stopped {
+            if let Some(old_album_art) = &self.old_album_art {
+                // keep showing the old art as is until the transition starts
+                let (alpha, offset) = if self.art_transition.is_some() {
+                    (art_frame.old_alpha, art_frame.old_offset * window_width)
+                } else {
+                    (1.0, 0.0)
+                };
+                if let Some((backdrop, backdrop_transform)) = &old_album_art.backdrop {
+                    canvas.draw(
+                        backdrop,
+                        burn_in_param(
+                            DrawParam {
+                                transform: shift_transform(*backdrop_transform, offset),
+                                color: fade(Color::WHITE, alpha),
+                                ..Default::default()
+                            },
+                            art_shift,
+                        ),
+                    );
+                }
+                self.draw_album_art(
+                    ctx,
+                    &mut canvas,
+                    &old_album_art.image,
+                    burn_in_param(
+                        DrawParam {
+                            transform: shift_transform(old_album_art.transform, offset),
+                            color: fade(Color::WHITE, alpha),
+                            ..Default::default()
+                        },
+                        art_shift,
+                    ),
+                )?;
+            }
+
+            if let Some(album_art) = &self.album_art
+                && let Some(draw_transform) = &self.album_art_draw_transform
+            {
+                let alpha = art_frame.new_alpha;
+                let offset = art_frame.new_offset * window_width;
+                if let Some(backdrop) = &self.album_art_backdrop
+                    && let Some(backdrop_transform) = &self.album_art_backdrop_transform
+                {
+                    canvas.draw(
+                        backdrop,
+                        burn_in_param(
+                            DrawParam {
+                                transform: shift_transform(*backdrop_transform, offset),
+                                color: fade(Color::WHITE, alpha),
+                                ..Default::default()
+                            },
+                            art_shift,
+                        ),
+                    );
+                }
+                self.draw_album_art(
+                    ctx,
+                    &mut canvas,
+                    album_art,
+                    burn_in_param(
+                        DrawParam {
+                            transform: shift_transform(*draw_transform, offset),
+                            color: fade(Color::WHITE, alpha),
+                            ..Default::default()
+                        },
+                        art_shift,
+                    ),
+                )?;
+            }
         }
 
         if !self.hide_text {
-            canvas.draw(&self.notice_text, DrawParam::default());
+            canvas.draw(
+                &self.notice_text,
+                burn_in_param(
+                    DrawParam::default().color(self.theme().default.text_color),
+                    text_shift,
+                ),
+            );
+            canvas.draw(
+                &self.art_notice_text,
+                burn_in_param(
+                    DrawParam::default()
+                        .dest([0.0, self.notice_text.dimensions(ctx).h])
+                        .color(self.theme().default.text_color),
+                    text_shift,
+                ),
+            );
 
             if self.mpd_play_state != MPDPlayState::Stopped && self.is_valid && self.is_initialized
             {
-                if let Some(mesh) = &self.text_bg_mesh {
-                    canvas.draw(mesh, DrawParam::default());
+                // keep sliding and scrolling text out of the album art panel
+                let text_area = self.get_text_area(ctx);
+                let is_clipped = self.get_split_side(ctx).is_some() && text_area.w >= 1.0;
+                if is_clipped {
+                    canvas.set_scissor_rect(text_area)?;
                 }
 
-                if !self.opts.disable_show_filename {
-                    canvas.draw(
-                        &self.filename_text,
-                        DrawParam {
-                            transform: self.filename_transform,
-                            ..Default::default()
-                        },
-                    );
+                if let Some(old_text) = &self.old_text {
+                    let alpha = text_frame.old_alpha;
+                    let offset = text_frame.old_offset * window_width;
+                    if let Some(mesh) = &old_text.bg_mesh {
+                        canvas.draw(
+                            mesh,
+                            burn_in_param(
+                                DrawParam::default()
+                                    .dest([offset, 0.0])
+                                    .color(fade(Color::WHITE, alpha)),
+                                text_shift,
+                            ),
+                        );
+                    }
+                    for (text, transform, color) in &old_text.rows {
+                        canvas.draw(
+                            text,
+                            burn_in_param(
+                                DrawParam {
+                                    transform: shift_transform(*transform, offset),
+                                    color: fade(*color, alpha),
+                                    ..Default::default()
+                                },
+                                text_shift,
+                            ),
+                        );
+                    }
                 }
 
-                if !self.opts.disable_show_album {
+                let text_alpha = text_frame.new_alpha;
+                let text_offset = text_frame.new_offset * window_width;
+                if let Some(mesh) = &self.text_bg_mesh {
                     canvas.draw(
-                        &self.album_text,
-                        DrawParam {
-                            transform: self.album_transform,
-                            ..Default::default()
-                        },
+                        mesh,
+                        burn_in_param(
+                            DrawParam::default()
+                                .dest([text_offset, 0.0])
+                                .color(fade(Color::WHITE, text_alpha)),
+                            text_shift,
+                        ),
                     );
                 }
+                if let Some(mesh) = &self.timer_bg_mesh {
+                    canvas.draw(mesh, burn_in_param(DrawParam::default(), text_shift));
+                }
 
-                if !self.opts.disable_show_artist {
+                for row in self.shown_song_rows() {
+                    let text = self.row_text(row);
                     canvas.draw(
-                        &self.artist_text,
-                        DrawParam {
-                            transform: self.artist_transform,
-                            ..Default::default()
-                        },
+                        text,
+                        burn_in_param(
+                            DrawParam {
+                                transform: shift_transform(
+                                    self.row_transform(row),
+                                    text_offset + self.get_marquee_offset(ctx, text),
+                                ),
+                                color: fade(self.theme().row(row).text_color, text_alpha),
+                                ..Default::default()
+                            },
+                            text_shift,
+                        ),
                     );
                 }
 
-                if !self.opts.disable_show_title {
-                    canvas.draw(
-                        &self.title_text,
-                        DrawParam {
-                            transform: self.title_transform,
-                            ..Default::default()
+                if is_clipped {
+                    canvas.set_default_scissor_rect();
+                }
+
+                if self.opts.show_progress_bar
+                    && let Some(progress_area) = self.get_progress_area(ctx)
+                {
+                    let bar_height = self.get_progress_bar_height(ctx);
+                    let progress = (self.timer / self.length).clamp(0.0, 1.0) as f32;
+                    let bar_bg = Mesh::new_rectangle(
+                        ctx,
+                        DrawMode::fill(),
+                        Rect {
+                            x: progress_area.x,
+                            y: progress_area.y,
+                            w: progress_area.w,
+                            h: bar_height,
                         },
-                    );
+                        self.theme().default.text_bg_color,
+                    )?;
+                    canvas.draw(&bar_bg, burn_in_param(DrawParam::default(), text_shift));
+                    if progress > 0.0 {
+                        let bar_fg = Mesh::new_rectangle(
+                            ctx,
+                            DrawMode::fill(),
+                            Rect {
+                                x: progress_area.x,
+                                y: progress_area.y,
+                                w: progress_area.w * progress,
+                                h: bar_height,
+                            },
+                            self.theme().default.text_color,
+                        )?;
+                        canvas.draw(&bar_fg, burn_in_param(DrawParam::default(), text_shift));
+                    }
                 }
 
-                if self.mpd_play_state == MPDPlayState::Playing {
+                if self.mpd_play_state == MPDPlayState::Playing && self.is_row_shown(TextRow::Timer)
+                {
                     canvas.draw(
                         &self.timer_text,
-                        DrawParam {
-                            transform: self.timer_transform,
-                            ..Default::default()
-                        },
+                        burn_in_param(
+                            DrawParam {
+                                transform: self.timer_transform,
+                                color: self.theme().timer.text_color,
+                                ..Default::default()
+                            },
+                            text_shift,
+                        ),
                     );
                 }
             }
         }
 
-        canvas.finish(ctx)
+        if self.gesture_ack_remaining > 0.0 {
+            let drawable_size = self.screen_size(ctx);
+            let alpha = (self.gesture_ack_remaining / GESTURE_ACK_FADE_TIME).min(1.0);
+            let ack_rect = self.gesture_ack_text.dimensions(ctx);
+            let padding = ack_rect.h * 0.25;
+            let x = (drawable_size.0.abs() - ack_rect.w) / 2.0;
+            let y = (drawable_size.1.abs() - ack_rect.h) / 2.0;
+            let plate = Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                Rect {
+                    x: x - padding,
+                    y: y - padding,
+                    w: ack_rect.w + padding * 2.0,
+                    h: ack_rect.h + padding * 2.0,
+                },
+                fade(self.theme().default.text_bg_color, alpha),
+            )?;
+            canvas.draw(&plate, burn_in_param(DrawParam::default(), text_shift));
+            canvas.draw(
+                &self.gesture_ack_text,
+                burn_in_param(
+                    DrawParam::default()
+                        .dest([x, y])
+                        .color(fade(self.theme().default.text_color, alpha)),
+                    text_shift,
+                ),
+            );
+        }
+
+        self.draw_dim_overlay(ctx, &mut canvas)?;
+
+        self.finish_scene(ctx, canvas)
     }
 
     fn key_down_event(
@@ -1122,11 +2564,22 @@ impl EventHandler for MPDDisplay {
             self.hide_text = true;
         } else if input.event.physical_key == PhysicalKey::Code(keyboard::KeyCode::Escape) {
             ctx.request_quit();
+        } else if input.event.physical_key == PhysicalKey::Code(keyboard::KeyCode::F11)
+            && let Err(e) = window::toggle_fullscreen(ctx)
+        {
+            log(e, debug_log::LogState::Warning, self.opts.log_level);
         }
 
         Ok(())
     }
 
+    fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, GameError> {
+        if let Err(e) = window::save_geometry(ctx) {
+            log(e, debug_log::LogState::Warning, self.opts.log_level);
+        }
+        Ok(false)
+    }
+
     fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> Result<(), GameError> {
         if input.event.physical_key == PhysicalKey::Code(keyboard::KeyCode::KeyH) {
             self.hide_text = false;
@@ -1135,16 +2588,146 @@ impl EventHandler for MPDDisplay {
         Ok(())
     }
 
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Result<(), GameError> {
+        if button != MouseButton::Left || !self.can_use_mouse() {
+            return Ok(());
+        }
+        self.resize_ticks = 0;
+        let (x, y) = self.to_scene_position(ctx, x, y);
+
+        if let Some(progress_area) = self.get_progress_area(ctx)
+            && progress_area.contains([x, y])
+        {
+            self.is_seeking = true;
+            self.seek_to_x(ctx, x, false);
+        } else {
+            self.toggle_pause();
+        }
+
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Result<(), GameError> {
+        if button == MouseButton::Left && self.is_seeking {
+            let (x, _) = self.to_scene_position(ctx, x, y);
+            self.is_seeking = false;
+            self.seek_to_x(ctx, x, true);
+        }
+
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> Result<(), GameError> {
+        if self.is_seeking {
+            self.resize_ticks = 0;
+            let (x, _) = self.to_scene_position(ctx, x, y);
+            if self.can_use_mouse() && self.mpd_play_state != MPDPlayState::Stopped {
+                self.seek_to_x(ctx, x, false);
+            } else {
+                self.is_seeking = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> Result<(), GameError> {
+        if !self.can_use_mouse() {
+            return Ok(());
+        }
+        if y > 0.0 {
+            self.change_volume(VOLUME_STEP);
+        } else if y < 0.0 {
+            self.change_volume(-VOLUME_STEP);
+        }
+
+        Ok(())
+    }
+
+    fn touch_event(
+        &mut self,
+        ctx: &mut Context,
+        phase: TouchPhase,
+        x: f64,
+        y: f64,
+    ) -> Result<(), GameError> {
+        let (x, y) = (x as f32, y as f32);
+        if self.opts.disable_touch_gestures {
+            // Behave like ggez's default and treat touches as the left mouse button
+            return match phase {
+                TouchPhase::Started => self.mouse_button_down_event(ctx, MouseButton::Left, x, y),
+                TouchPhase::Moved => self.mouse_motion_event(ctx, x, y, 0.0, 0.0),
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    self.mouse_button_up_event(ctx, MouseButton::Left, x, y)
+                }
+            };
+        }
+        if !self.can_send_commands() {
+            return Ok(());
+        }
+        self.resize_ticks = 0;
+        let (x, y) = self.to_scene_position(ctx, x, y);
+
+        // Touches starting on the progress area seek instead of being gestures
+        if self.is_seeking {
+            match phase {
+                TouchPhase::Started => (),
+                TouchPhase::Moved => self.seek_to_x(ctx, x, false),
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    self.is_seeking = false;
+                    self.seek_to_x(ctx, x, true);
+                }
+            }
+            return Ok(());
+        } else if phase == TouchPhase::Started
+            && self.gesture_recognizer.is_idle()
+            && let Some(progress_area) = self.get_progress_area(ctx)
+            && progress_area.contains([x, y])
+        {
+            self.is_seeking = true;
+            self.seek_to_x(ctx, x, false);
+            return Ok(());
+        }
+
+        if let Some(gesture) = self.gesture_recognizer.touch(phase, x, y, Instant::now()) {
+            self.handle_gesture(ctx, gesture);
+        }
+
+        Ok(())
+    }
+
     fn resize_event(
         &mut self,
         ctx: &mut Context,
         _width: f32,
         _height: f32,
     ) -> Result<(), GameError> {
-        self.get_album_art_transform(ctx, !self.opts.do_not_fill_scale_album_art);
+        self.get_album_art_transform(ctx);
         self.refresh_text_transforms(ctx)
             .expect("Failed to set text transforms");
         self.resize_ticks = 0;
+        self.resize_instant = Instant::now();
+        // the art may now be drawn larger than it was decoded
+        self.is_art_redecode_needed |= self.is_album_art_downscaled;
 
         Ok(())
     }
@@ -0,0 +1,243 @@
+use ggez::winit::event::TouchPhase;
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    Tap,
+    LongPress,
+    SwipeLeft,
+    SwipeRight,
+    TwoFingerSwipeUp,
+    TwoFingerSwipeDown,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GestureThresholds {
+    pub swipe_distance: f32,
+    pub tap_distance: f32,
+    pub long_press: Duration,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TouchPoint {
+    start: (f32, f32),
+    current: (f32, f32),
+}
+
+impl TouchPoint {
+    fn displacement(&self) -> (f32, f32) {
+        (self.current.0 - self.start.0, self.current.1 - self.start.1)
+    }
+
+    fn distance_to(&self, x: f32, y: f32) -> f32 {
+        ((self.current.0 - x).powi(2) + (self.current.1 - y).powi(2)).sqrt()
+    }
+}
+
+/// Turns the raw touch events from ggez into gestures.
+///
+/// ggez does not pass on touch ids, so each event is matched to the nearest
+/// active touch point to follow individual fingers.
+pub struct GestureRecognizer {
+    thresholds: GestureThresholds,
+    active: Vec<TouchPoint>,
+    finished: Vec<TouchPoint>,
+    start_instant: Option<Instant>,
+    max_touches: usize,
+    long_press_fired: bool,
+}
+
+impl GestureRecognizer {
+    pub fn new(thresholds: GestureThresholds) -> Self {
+        Self {
+            thresholds,
+            active: Vec::new(),
+            finished: Vec::new(),
+            start_instant: None,
+            max_touches: 0,
+            long_press_fired: false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    fn nearest_active(&self, x: f32, y: f32) -> Option<usize> {
+        self.active
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.distance_to(x, y).total_cmp(&b.distance_to(x, y)))
+            .map(|(idx, _)| idx)
+    }
+
+    pub fn touch(&mut self, phase: TouchPhase, x: f32, y: f32, now: Instant) -> Option<Gesture> {
+        match phase {
+            TouchPhase::Started => {
+                if self.active.is_empty() {
+                    self.finished.clear();
+                    self.start_instant = Some(now);
+                    self.max_touches = 0;
+                    self.long_press_fired = false;
+                }
+                self.active.push(TouchPoint {
+                    start: (x, y),
+                    current: (x, y),
+                });
+                self.max_touches = self.max_touches.max(self.active.len());
+                None
+            }
+            TouchPhase::Moved => {
+                if let Some(idx) = self.nearest_active(x, y) {
+                    self.active[idx].current = (x, y);
+                }
+                None
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let idx = self.nearest_active(x, y)?;
+                let mut point = self.active.remove(idx);
+                point.current = (x, y);
+                self.finished.push(point);
+                if !self.active.is_empty() || phase == TouchPhase::Cancelled {
+                    return None;
+                }
+                self.classify(now)
+            }
+        }
+    }
+
+    /// Must be called regularly, as a long press is recognized while the
+    /// finger is still down.
+    pub fn poll(&mut self, now: Instant) -> Option<Gesture> {
+        if self.long_press_fired || self.max_touches != 1 || self.active.len() != 1 {
+            return None;
+        }
+        let (dx, dy) = self.active[0].displacement();
+        let held = now.duration_since(self.start_instant?);
+        if held >= self.thresholds.long_press && dx.hypot(dy) < self.thresholds.tap_distance {
+            self.long_press_fired = true;
+            return Some(Gesture::LongPress);
+        }
+        None
+    }
+
+    fn classify(&mut self, now: Instant) -> Option<Gesture> {
+        if self.long_press_fired || self.finished.is_empty() {
+            return None;
+        }
+
+        let count = self.finished.len() as f32;
+        let (dx, dy) = self
+            .finished
+            .iter()
+            .map(|point| point.displacement())
+            .fold((0.0, 0.0), |acc, d| {
+                (acc.0 + d.0 / count, acc.1 + d.1 / count)
+            });
+
+        if self.max_touches >= 2 {
+            if dy.abs() >= self.thresholds.swipe_distance && dy.abs() > dx.abs() {
+                return Some(if dy < 0.0 {
+                    Gesture::TwoFingerSwipeUp
+                } else {
+                    Gesture::TwoFingerSwipeDown
+                });
+            }
+            return None;
+        }
+
+        if dx.abs() >= self.thresholds.swipe_distance && dx.abs() > dy.abs() {
+            Some(if dx < 0.0 {
+                Gesture::SwipeLeft
+            } else {
+                Gesture::SwipeRight
+            })
+        } else if dx.hypot(dy) < self.thresholds.tap_distance
+            && now.duration_since(self.start_instant?) < self.thresholds.long_press
+        {
+            Some(Gesture::Tap)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognizer() -> GestureRecognizer {
+        GestureRecognizer::new(GestureThresholds {
+            swipe_distance: 100.0,
+            tap_distance: 20.0,
+            long_press: Duration::from_millis(500),
+        })
+    }
+
+    #[test]
+    fn test_tap_and_swipe() {
+        let mut r = recognizer();
+        let t = Instant::now();
+        assert_eq!(r.touch(TouchPhase::Started, 50.0, 50.0, t), None);
+        assert_eq!(
+            r.touch(
+                TouchPhase::Ended,
+                52.0,
+                51.0,
+                t + Duration::from_millis(100)
+            ),
+            Some(Gesture::Tap)
+        );
+
+        assert_eq!(r.touch(TouchPhase::Started, 300.0, 50.0, t), None);
+        r.touch(TouchPhase::Moved, 200.0, 60.0, t);
+        assert_eq!(
+            r.touch(
+                TouchPhase::Ended,
+                150.0,
+                60.0,
+                t + Duration::from_millis(200)
+            ),
+            Some(Gesture::SwipeLeft)
+        );
+    }
+
+    #[test]
+    fn test_long_press() {
+        let mut r = recognizer();
+        let t = Instant::now();
+        r.touch(TouchPhase::Started, 50.0, 50.0, t);
+        assert_eq!(r.poll(t + Duration::from_millis(100)), None);
+        assert_eq!(
+            r.poll(t + Duration::from_millis(600)),
+            Some(Gesture::LongPress)
+        );
+        assert_eq!(r.poll(t + Duration::from_millis(700)), None);
+        assert_eq!(
+            r.touch(
+                TouchPhase::Ended,
+                50.0,
+                50.0,
+                t + Duration::from_millis(800)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_two_finger_swipe() {
+        let mut r = recognizer();
+        let t = Instant::now();
+        r.touch(TouchPhase::Started, 100.0, 400.0, t);
+        r.touch(TouchPhase::Started, 200.0, 400.0, t);
+        r.touch(TouchPhase::Moved, 100.0, 300.0, t);
+        r.touch(TouchPhase::Moved, 200.0, 300.0, t);
+        r.touch(TouchPhase::Moved, 100.0, 250.0, t);
+        r.touch(TouchPhase::Moved, 200.0, 250.0, t);
+        assert_eq!(r.touch(TouchPhase::Ended, 100.0, 250.0, t), None);
+        assert_eq!(
+            r.touch(TouchPhase::Ended, 200.0, 250.0, t),
+            Some(Gesture::TwoFingerSwipeUp)
+        );
+    }
+}
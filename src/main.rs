@@ -1,23 +1,32 @@
+mod album_art;
+mod booklet;
+mod burn_in;
+mod clock;
+mod config;
 mod debug_log;
 mod display;
+mod gesture;
 mod mpd_handler;
+mod palette;
+mod placeholder;
 mod signal;
+mod template;
+mod transition;
 #[cfg(feature = "unicode_support")]
 mod unicode_support;
+mod window;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use ggez::conf::{WindowMode, WindowSetup};
 use ggez::event;
 use ggez::{ContextBuilder, GameResult};
-use std::fs::File;
-use std::io::Read;
-use std::net::Ipv4Addr;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Opt {
-    host: Ipv4Addr,
+    #[arg(help = "IPv4 address of MPD, or the path of its local socket")]
+    host: Option<config::Host>,
     #[arg(default_value = "6600")]
     port: u16,
     #[arg(short = 'p')]
@@ -43,9 +52,196 @@ pub struct Opt {
     password_file: Option<PathBuf>,
     #[arg(
         long = "no-scale-fill",
-        help = "don't scale-fill the album art to the window"
+        help = "don't scale-fill the album art to the window (same as \"--art-fit original\")"
     )]
     do_not_fill_scale_album_art: bool,
+    #[arg(
+        long = "art-fit",
+        help = "how to scale the album art to the window: fit inside it (\"contain\"), fill it and crop (\"cover\"), fill it ignoring the aspect ratio (\"stretch\"), don't scale (\"original\") or scale by a whole number (\"integer\")",
+        value_enum,
+        default_value = "contain"
+    )]
+    art_fit: config::ArtFit,
+    #[arg(
+        long = "art-anchor",
+        help = "which part of the album art stays in view when it is cropped",
+        value_enum,
+        default_value = "center"
+    )]
+    art_anchor: config::ArtAnchor,
+    #[arg(
+        long = "art-max-size",
+        help = "largest width or height in pixels the album art is decoded at (default the window size)"
+    )]
+    art_max_size: Option<u32>,
+    #[arg(
+        long = "disable-art-animation",
+        help = "show only the first frame of animated album art"
+    )]
+    disable_art_animation: bool,
+    #[arg(
+        long = "art-animation-memory",
+        help = "memory in MiB animated album art may take before only its first frame is shown",
+        default_value = "64"
+    )]
+    art_animation_memory: u32,
+    #[arg(
+        long = "disable-placeholder-art",
+        help = "show nothing instead of generated art for songs without album art"
+    )]
+    disable_placeholder_art: bool,
+    #[arg(
+        long = "art-sources",
+        help = "where to look for album art, in order: in the song file (\"embedded\"), a cover file next to the song through MPD (\"mpd-folder\") or under \"--music-dir\" (\"local-dir\"), or art generated from the album and artist (\"placeholder\")",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "embedded,mpd-folder,local-dir,placeholder"
+    )]
+    art_sources: Vec<config::ArtSource>,
+    #[arg(
+        long = "music-dir",
+        help = "MPD's music directory on this machine, for the \"local-dir\" art source"
+    )]
+    music_dir: Option<PathBuf>,
+    #[arg(
+        long = "art-names",
+        help = "album art file names for the \"local-dir\" art source, in order (\"*\" and \"?\" match any characters)",
+        value_delimiter = ',',
+        default_value = "cover.*,folder.*,front.*,albumart*.*"
+    )]
+    art_names: Vec<String>,
+    #[arg(
+        long = "booklet-slideshow",
+        help = "cycle through the images in the album directory under \"music-dir\""
+    )]
+    booklet_slideshow: bool,
+    #[arg(
+        long = "booklet-interval",
+        help = "seconds each image of the booklet slideshow is shown",
+        default_value = "10"
+    )]
+    booklet_interval: f32,
+    #[arg(
+        long = "booklet-scans",
+        help = "also show the images in a \"scans\" folder of the album directory"
+    )]
+    booklet_scans: bool,
+    #[arg(
+        long = "art-backdrop",
+        help = "draw a blurred and darkened copy of the album art behind it"
+    )]
+    art_backdrop: bool,
+    #[arg(
+        long = "art-backdrop-blur",
+        help = "blur radius of the backdrop as a percentage of the album art size",
+        default_value = "4"
+    )]
+    art_backdrop_blur: f32,
+    #[arg(
+        long = "art-backdrop-brightness",
+        help = "brightness of the backdrop as a percentage (0-100)",
+        default_value = "40"
+    )]
+    art_backdrop_brightness: u8,
+    #[arg(
+        long = "adaptive-theme",
+        help = "pick the colors from the album art instead of the theme"
+    )]
+    adaptive_theme: bool,
+    #[arg(
+        long = "transition",
+        help = "transition between the album art and text of consecutive songs",
+        value_enum,
+        default_value = "none"
+    )]
+    transition: transition::TransitionKind,
+    #[arg(
+        long = "transition-time",
+        help = "length of the transitions in seconds",
+        default_value = "0.5"
+    )]
+    transition_time: f32,
+    #[arg(
+        long = "text-overflow",
+        help = "how to fit text rows that are too wide for the window: \"shrink\" them, or scroll them at the same font size as the other rows with \"marquee\"",
+        value_enum,
+        default_value = "shrink"
+    )]
+    text_overflow: config::TextOverflow,
+    #[arg(
+        long = "marquee-speed",
+        help = "scrolling speed of marquee text in pixels per second",
+        default_value = "60"
+    )]
+    marquee_speed: f32,
+    #[arg(
+        long = "marquee-pause",
+        help = "seconds marquee text pauses at either end",
+        default_value = "1.5"
+    )]
+    marquee_pause: f32,
+    #[arg(
+        long = "wrap-lines",
+        help = "wrap text rows onto up to this many lines before shrinking them",
+        default_value = "1"
+    )]
+    wrap_lines: u32,
+    #[arg(
+        long = "idle-screen",
+        help = "show a clock instead of a black window while MPD is stopped"
+    )]
+    idle_screen: bool,
+    #[arg(
+        long = "idle-after-pause",
+        help = "also show the idle screen once MPD has been paused for this many seconds"
+    )]
+    idle_after_pause: Option<f32>,
+    #[arg(
+        long = "idle-stats",
+        help = "show the song, album and artist counts and uptime of MPD on the idle screen"
+    )]
+    idle_stats: bool,
+    #[arg(
+        long = "clock-format",
+        help = "format of the idle screen clock (%H, %I, %M, %S and %p are replaced)",
+        default_value = "%H:%M"
+    )]
+    clock_format: String,
+    #[arg(
+        long = "date-format",
+        help = "format of the idle screen date (%Y, %m, %d, %e, %a, %A, %b and %B are replaced)",
+        default_value = "%A, %B %e"
+    )]
+    date_format: String,
+    #[arg(
+        long = "pixel-shift",
+        help = "move everything by up to this many pixels every \"pixel-shift-interval\" seconds against burn-in (0 disables)",
+        default_value = "0"
+    )]
+    pixel_shift: u32,
+    #[arg(
+        long = "pixel-shift-interval",
+        help = "seconds between pixel shifts",
+        default_value = "60"
+    )]
+    pixel_shift_interval: f32,
+    #[arg(
+        long = "text-drift",
+        help = "let the text slowly drift by up to this many pixels against burn-in (0 disables)",
+        default_value = "0"
+    )]
+    text_drift: u32,
+    #[arg(
+        long = "dim-after",
+        help = "dim the window once MPD has been paused or stopped for this many seconds"
+    )]
+    dim_after: Option<f32>,
+    #[arg(
+        long = "dim-brightness",
+        help = "brightness of the dimmed window as a percentage (0-100)",
+        default_value = "30"
+    )]
+    dim_brightness: u8,
     #[arg(short = 'l', long = "log-level", default_value = "error")]
     log_level: debug_log::LogLevel,
     #[arg(
@@ -55,39 +251,104 @@ pub struct Opt {
         default_value = "190"
     )]
     text_bg_opacity: u8,
+    #[arg(
+        long = "disable-mouse-control",
+        help = "disable seeking, pausing and volume control with the mouse"
+    )]
+    disable_mouse_control: bool,
+    #[arg(
+        long = "show-progress-bar",
+        help = "show a progress bar above the timer (clicking it seeks)"
+    )]
+    show_progress_bar: bool,
+    #[arg(
+        long = "disable-touch-gestures",
+        help = "treat touches as mouse clicks instead of recognizing gestures"
+    )]
+    disable_touch_gestures: bool,
+    #[arg(
+        long = "touch-swipe-distance",
+        help = "distance in pixels a touch must travel to count as a swipe",
+        default_value = "80"
+    )]
+    touch_swipe_distance: f32,
+    #[arg(
+        long = "touch-tap-distance",
+        help = "maximum distance in pixels a touch may travel to count as a tap or long-press",
+        default_value = "20"
+    )]
+    touch_tap_distance: f32,
+    #[arg(
+        long = "touch-long-press-ms",
+        help = "milliseconds a touch must be held to count as a long-press",
+        default_value = "700"
+    )]
+    touch_long_press_ms: u64,
+    #[arg(long = "fullscreen", help = "start fullscreen (F11 toggles it)")]
+    fullscreen: bool,
+    #[arg(
+        long = "rotate",
+        help = "turn the picture clockwise by this many degrees, for displays mounted sideways",
+        value_enum,
+        default_value = "0"
+    )]
+    rotate: config::Rotation,
+    #[arg(long = "borderless", help = "open the window without decorations")]
+    borderless: bool,
+    #[arg(
+        long = "geometry",
+        help = "size and position of the window as WxH or WxH+X+Y (default the size and position it last had)"
+    )]
+    geometry: Option<window::Geometry>,
+    #[arg(
+        long = "monitor",
+        help = "index of the monitor to open the window on, starting at 0"
+    )]
+    monitor: Option<usize>,
+    #[arg(long = "always-on-top", help = "keep the window above other windows")]
+    always_on_top: bool,
+    #[arg(
+        long = "window-title",
+        help = "template for the window title, with the song fields of the text rows and {state}",
+        default_value = "[{artist} – ]{title}[ ({state})]"
+    )]
+    window_title: template::Template,
+    #[arg(
+        long = "config",
+        help = "config file to use (default $XDG_CONFIG_HOME/mpd_info_screen/config.toml)"
+    )]
+    config: Option<PathBuf>,
+    #[arg(long = "profile", help = "named profile from the config file to use")]
+    profile: Option<String>,
+    #[arg(skip)]
+    theme: config::Theme,
+    #[arg(skip)]
+    layout: config::Layout,
+    #[arg(skip)]
+    templates: config::Templates,
 }
 
 fn main() -> GameResult<()> {
-    let mut opt = Opt::parse();
-    if let Some(forced_scale) = &mut opt.force_text_height_scale {
-        if *forced_scale < 0.01 {
-            *forced_scale = 0.01;
-            println!("WARNING: Clamped \"force-text-height-scale\" to minimum of 0.01!");
-        } else if *forced_scale > 0.5 {
-            *forced_scale = 0.5;
-            println!("WARNING: Clamped \"force-text-height-scale\" to maximum of 0.5!");
-        }
-    }
-    println!("Got host addr == {}, port == {}", opt.host, opt.port);
-
-    // Read password from file if exists, error otherwise.
-    if let Some(psswd_file_path) = opt.password_file.as_ref() {
-        let mut file = File::open(psswd_file_path).expect("pfile/password_file should exist");
-        let mut content: String = String::new();
-
-        file.read_to_string(&mut content)
-            .expect("Should be able to read from pfile/password_file");
-
-        if content.ends_with("\r\n") {
-            content.truncate(content.len() - 2);
-        } else if content.ends_with('\n') {
-            content.truncate(content.len() - 1);
-        }
+    let config_source =
+        config::ConfigSource::new(Opt::command().get_matches()).unwrap_or_else(|e| {
+            Opt::command()
+                .error(clap::error::ErrorKind::InvalidValue, e)
+                .exit()
+        });
+    let opt = config_source.load().unwrap_or_else(|e| {
+        debug_log::log_error(e);
+        std::process::exit(1);
+    });
+    println!(
+        "Got host addr == {}, port == {}",
+        opt.host
+            .as_ref()
+            .expect("host should be set after loading the config"),
+        opt.port
+    );
 
-        opt.password = Some(content);
-    }
-
-    // Set up signal handlers to request graceful shutdown.
+    // Set up signal handlers to request graceful shutdown, or a config reload
+    // on SIGHUP.
     #[cfg(target_family = "unix")]
     {
         signal::register_signal(libc::SIGHUP).unwrap();
@@ -97,23 +358,36 @@ fn main() -> GameResult<()> {
     #[cfg(target_family = "windows")]
     {}
 
+    let geometry = opt.geometry.or_else(|| {
+        // the saved position is on the desktop, not relative to "--monitor"
+        window::load_saved_geometry().map(|saved| window::Geometry {
+            position: saved.position.filter(|_| opt.monitor.is_none()),
+            ..saved
+        })
+    });
+    let mut window_mode = WindowMode {
+        resizable: true,
+        resize_on_scale_factor_change: true,
+        borderless: opt.borderless,
+        ..Default::default()
+    };
+    if let Some(geometry) = geometry {
+        window_mode = window_mode.dimensions(geometry.width as f32, geometry.height as f32);
+    }
     let (mut ctx, event_loop) = ContextBuilder::new("mpd_info_screen", "Stephen Seo")
         .window_setup(WindowSetup {
-            title: "mpd info screen".into(),
-            ..Default::default()
-        })
-        .window_mode(WindowMode {
-            resizable: true,
-            resize_on_scale_factor_change: true,
+            title: window::DEFAULT_TITLE.into(),
             ..Default::default()
         })
+        .window_mode(window_mode)
         .build()
         .expect("Failed to create ggez context");
+    window::setup(&mut ctx, &opt, geometry);
 
     // mount "/" read-only so that fonts can be loaded via absolute paths
     ctx.fs.mount(&PathBuf::from("/"), true);
 
-    let display = display::MPDDisplay::new(&mut ctx, opt.clone());
+    let display = display::MPDDisplay::new(&mut ctx, opt, config_source);
 
     event::run(ctx, event_loop, display)
 }
@@ -1,7 +1,12 @@
+use crate::config::{ArtSource, Host};
 use crate::debug_log::{LogLevel, LogState, log};
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Write;
 use std::io::{self, Read, Write as IOWrite};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+#[cfg(target_family = "unix")]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
@@ -23,6 +28,95 @@ enum PollState {
     Status,
     ReadPicture,
     ReadPictureInDir,
+    Stats,
+    Config,
+    Command,
+}
+
+/// The connection to MPD, over TCP or its local socket.
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(target_family = "unix")]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn open(host: &Host, port: u16) -> Result<Self, String> {
+        match host {
+            Host::Ip(addr) => TcpStream::connect_timeout(
+                &SocketAddr::new(IpAddr::V4(*addr), port),
+                CONNECT_TIMEOUT,
+            )
+            .map(Connection::Tcp)
+            .map_err(|_| String::from("Failed to get TCP connection (is MPD running?)")),
+            #[cfg(target_family = "unix")]
+            Host::Socket(path) => UnixStream::connect(path)
+                .map(Connection::Unix)
+                .map_err(|e| {
+                    format!(
+                        "Failed to connect to {} (is MPD running?): {e}",
+                        path.display()
+                    )
+                }),
+            #[cfg(not(target_family = "unix"))]
+            Host::Socket(_) => Err(String::from(
+                "Connecting to MPD's local socket is not supported on this platform",
+            )),
+        }
+    }
+
+    /// MPD only answers "config" over its local socket.
+    fn is_local(&self) -> bool {
+        match self {
+            Connection::Tcp(_) => false,
+            #[cfg(target_family = "unix")]
+            Connection::Unix(_) => true,
+        }
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(target_family = "unix")]
+            Connection::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.shutdown(std::net::Shutdown::Both),
+            #[cfg(target_family = "unix")]
+            Connection::Unix(stream) => stream.shutdown(std::net::Shutdown::Both),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(target_family = "unix")]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl IOWrite for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(target_family = "unix")]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(target_family = "unix")]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -32,6 +126,40 @@ pub enum MPDPlayState {
     Stopped,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum MPDCommand {
+    SeekCur(f64),
+    Pause(bool),
+    Play,
+    Next,
+    Previous,
+    SetVolume(u8),
+}
+
+impl MPDCommand {
+    fn to_protocol_string(&self) -> String {
+        match self {
+            MPDCommand::SeekCur(seconds) => format!("seekcur {seconds:.3}\n"),
+            MPDCommand::Pause(true) => String::from("pause 1\n"),
+            MPDCommand::Pause(false) => String::from("pause 0\n"),
+            MPDCommand::Play => String::from("play\n"),
+            MPDCommand::Next => String::from("next\n"),
+            MPDCommand::Previous => String::from("previous\n"),
+            MPDCommand::SetVolume(volume) => format!("setvol {volume}\n"),
+        }
+    }
+}
+
+/// Counts from the "stats" command, shown on the idle screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MPDStats {
+    pub artists: u64,
+    pub albums: u64,
+    pub songs: u64,
+    /// Seconds since MPD started.
+    pub uptime: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct InfoFromShared {
     pub filename: String,
@@ -42,6 +170,42 @@ pub struct InfoFromShared {
     pub pos: f64,
     pub error_text: String,
     pub mpd_play_state: MPDPlayState,
+    pub volume: i32,
+    /// Tags of the current song, with lowercase keys.
+    pub tags: BTreeMap<String, String>,
+    pub stats: Option<MPDStats>,
+    /// MPD's music directory, if it reported one over its local socket.
+    pub music_dir: Option<PathBuf>,
+}
+
+impl InfoFromShared {
+    /// Looks up a field for the text row templates. "track" and "disc" tags
+    /// like "3/12" are split into "track" and "totaltracks", and "state" is
+    /// "paused" or "stopped", or empty while playing.
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        let split_tag = |tag: &str, is_total: bool| {
+            let value = self.tags.get(tag)?;
+            let mut split = value.splitn(2, '/');
+            let number = split.next();
+            if is_total { split.next() } else { number }.map(str::trim)
+        };
+        match name {
+            "title" => Some(&self.title),
+            "artist" => Some(&self.artist),
+            "album" => Some(&self.album),
+            "filename" | "file" => Some(&self.filename),
+            "track" => split_tag("track", false),
+            "totaltracks" => split_tag("track", true),
+            "disc" => split_tag("disc", false),
+            "totaldiscs" => split_tag("disc", true),
+            "state" => Some(match self.mpd_play_state {
+                MPDPlayState::Playing => "",
+                MPDPlayState::Paused => "paused",
+                MPDPlayState::Stopped => "stopped",
+            }),
+            _ => self.tags.get(name).map(String::as_str),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -59,15 +223,21 @@ pub struct MPDHandlerState {
     current_song_title: String,
     current_song_artist: String,
     current_song_album: String,
+    current_song_tags: BTreeMap<String, String>,
     current_song_length: f64,
     current_song_position: f64,
+    current_volume: i32,
     current_binary_size: usize,
     poll_state: PollState,
-    stream: TcpStream,
+    stream: Connection,
+    host: Host,
+    port: u16,
     password: String,
     error_text: String,
     can_authenticate: bool,
     is_authenticated: bool,
+    /// The sources fetched through MPD, in the order they are tried.
+    art_sources: Vec<ArtSource>,
     can_get_album_art: bool,
     can_get_album_art_in_dir: bool,
     can_get_status: bool,
@@ -78,12 +248,21 @@ pub struct MPDHandlerState {
     song_title_get_time: Instant,
     song_pos_get_time: Instant,
     song_length_get_time: Instant,
+    /// Set when "stats" is polled while MPD isn't playing.
+    can_get_stats: bool,
+    stats: Option<MPDStats>,
+    stats_get_time: Instant,
+    /// Set until "config" was sent, which MPD only answers over its local
+    /// socket.
+    can_get_config: bool,
+    music_dir: Option<PathBuf>,
     self_thread: SelfThreadT,
     dirty_flag: Arc<AtomicBool>,
     pub stop_flag: Arc<AtomicBool>,
     log_level: LogLevel,
     mpd_play_state: MPDPlayState,
     recv_zero_bytes_count: u32,
+    pending_commands: VecDeque<MPDCommand>,
 }
 
 fn check_next_chars(
@@ -248,20 +427,16 @@ fn restart_stream(
     thread::sleep(PRE_RESTART_WAIT);
 
     let fn_impl = |state_handle: &mut RwLockWriteGuard<'_, MPDHandlerState>| -> Result<(), String> {
-        let peer = state_handle
-            .stream
-            .peer_addr()
-            .map_err(|_| String::from("Failed to get TCP stream peer addr/port"))?;
         state_handle
             .stream
-            .shutdown(std::net::Shutdown::Both)
-            .map_err(|_| String::from("Failed to cleanup TCP stream"))?;
-        state_handle.stream = TcpStream::connect_timeout(&peer, CONNECT_TIMEOUT)
-            .map_err(|_| String::from("Failed to reconnect"))?;
+            .shutdown()
+            .map_err(|_| String::from("Failed to cleanup stream"))?;
+        state_handle.stream = Connection::open(&state_handle.host, state_handle.port)
+            .map_err(|e| format!("Failed to reconnect: {e}"))?;
         state_handle
             .stream
             .set_nonblocking(true)
-            .map_err(|_| String::from("Failed to set non-blocking on restarted TCP stream"))?;
+            .map_err(|_| String::from("Failed to set non-blocking on restarted stream"))?;
         Ok(())
     };
 
@@ -281,16 +456,21 @@ fn restart_stream(
 
 impl MPDHandler {
     pub fn new(
-        host: Ipv4Addr,
+        host: Host,
         port: u16,
         password: String,
+        art_sources: &[ArtSource],
+        fetch_stats: bool,
         log_level: LogLevel,
     ) -> Result<Self, String> {
-        let stream =
-            TcpStream::connect_timeout(&SocketAddr::new(IpAddr::V4(host), port), CONNECT_TIMEOUT)
-                .map_err(|_| String::from("Failed to get TCP connection (is MPD running?)"))?;
+        let stream = Connection::open(&host, port)?;
 
         let password_is_empty = password.is_empty();
+        let art_sources: Vec<ArtSource> = art_sources
+            .iter()
+            .copied()
+            .filter(|source| matches!(source, ArtSource::Embedded | ArtSource::MpdFolder))
+            .collect();
 
         let s = MPDHandler {
             state: Arc::new(RwLock::new(MPDHandlerState {
@@ -302,15 +482,21 @@ impl MPDHandler {
                 current_song_artist: String::new(),
                 current_song_length: 0.0,
                 current_song_position: 0.0,
+                current_volume: -1,
                 current_binary_size: 0,
                 poll_state: PollState::None,
+                can_get_config: stream.is_local(),
+                music_dir: None,
                 stream,
+                host,
+                port,
                 password,
                 error_text: String::new(),
                 can_authenticate: true,
                 is_authenticated: password_is_empty,
-                can_get_album_art: true,
-                can_get_album_art_in_dir: true,
+                can_get_album_art: art_sources.contains(&ArtSource::Embedded),
+                can_get_album_art_in_dir: art_sources.contains(&ArtSource::MpdFolder),
+                art_sources,
                 can_get_status: true,
                 is_init: true,
                 did_check_overtime: false,
@@ -319,13 +505,18 @@ impl MPDHandler {
                 song_title_get_time: Instant::now().checked_sub(Duration::from_secs(10)).unwrap(),
                 song_pos_get_time: Instant::now().checked_sub(Duration::from_secs(10)).unwrap(),
                 song_length_get_time: Instant::now().checked_sub(Duration::from_secs(10)).unwrap(),
+                can_get_stats: fetch_stats,
+                stats: None,
+                stats_get_time: Instant::now().checked_sub(Duration::from_secs(10)).unwrap(),
                 self_thread: None,
                 dirty_flag: Arc::new(AtomicBool::new(true)),
                 stop_flag: Arc::new(AtomicBool::new(false)),
                 log_level,
                 mpd_play_state: MPDPlayState::Stopped,
                 current_song_album: String::new(),
+                current_song_tags: BTreeMap::new(),
                 recv_zero_bytes_count: 0,
+                pending_commands: VecDeque::new(),
             })),
         };
 
@@ -351,11 +542,15 @@ impl MPDHandler {
                 title: read_lock.current_song_title.clone(),
                 artist: read_lock.current_song_artist.clone(),
                 album: read_lock.current_song_album.clone(),
+                tags: read_lock.current_song_tags.clone(),
                 length: read_lock.current_song_length,
                 pos: read_lock.current_song_position
                     + read_lock.song_pos_get_time.elapsed().as_secs_f64(),
                 error_text: read_lock.error_text.clone(),
                 mpd_play_state: read_lock.mpd_play_state,
+                volume: read_lock.current_volume,
+                stats: read_lock.stats,
+                music_dir: read_lock.music_dir.clone(),
             });
         }
 
@@ -417,12 +612,23 @@ impl MPDHandler {
         Ok(())
     }
 
-    pub fn force_try_other_album_art(&self) -> Result<(), ()> {
-        let mut write_handle = self.state.try_write().map_err(|_| ())?;
+    pub fn queue_command(&self, command: MPDCommand) {
+        if let Ok(mut write_lock) = self.state.write() {
+            write_lock.pending_commands.push_back(command);
+        }
+    }
+
+    /// Gives up on the given source for the current song, so that the next
+    /// one is fetched.
+    pub fn skip_art_source(&self, source: ArtSource) -> Result<(), ()> {
+        let mut write_handle = self.state.write().map_err(|_| ())?;
         write_handle.art_data.clear();
         write_handle.art_data_size = 0;
-        write_handle.can_get_album_art = false;
-        write_handle.can_get_album_art_in_dir = true;
+        match source {
+            ArtSource::Embedded => write_handle.can_get_album_art = false,
+            ArtSource::MpdFolder => write_handle.can_get_album_art_in_dir = false,
+            _ => (),
+        }
         Ok(())
     }
 
@@ -441,7 +647,7 @@ impl MPDHandler {
                 write_handle
                     .stream
                     .set_nonblocking(true)
-                    .map_err(|_| String::from("Failed to set non-blocking on TCP stream"))?;
+                    .map_err(|_| String::from("Failed to set non-blocking on stream"))?;
                 break;
             } else {
                 thread::sleep(POLL_DURATION);
@@ -520,7 +726,7 @@ impl MPDHandler {
         let read_result = write_handle.stream.read(buf);
         if let Err(io_err) = read_result {
             if io_err.kind() != io::ErrorKind::WouldBlock {
-                return Err(format!("TCP stream error: {io_err}"));
+                return Err(format!("Stream error: {io_err}"));
             } else {
                 return Ok(());
             }
@@ -537,7 +743,7 @@ impl MPDHandler {
                     );
                     return restart_stream(&mut write_handle, log_level);
                 }
-                return Err(String::from("Got zero bytes from TCP stream"));
+                return Err(String::from("Got zero bytes from stream"));
             } else {
                 write_handle.recv_zero_bytes_count = 0;
                 read_amount = read_amount_result;
@@ -545,7 +751,7 @@ impl MPDHandler {
         }
         let mut buf_vec: Vec<u8> = Vec::from(&buf[0..read_amount]);
 
-        let mut got_mpd_state: MPDPlayState = MPDPlayState::Playing;
+        let mut got_mpd_state: Option<MPDPlayState> = None;
 
         'handle_buf: loop {
             if write_handle.current_binary_size > 0 {
@@ -612,16 +818,21 @@ impl MPDHandler {
                     );
                     match write_handle.poll_state {
                         PollState::Password => write_handle.is_authenticated = true,
-                        PollState::ReadPicture => {
-                            if write_handle.art_data.is_empty() {
-                                write_handle.can_get_album_art = false;
-                                write_handle.dirty_flag.store(true, Ordering::Release);
-                                log(
-                                    "No embedded album art",
-                                    LogState::Warning,
-                                    write_handle.log_level,
-                                );
-                            }
+                        PollState::Command => {
+                            write_handle.force_get_status = true;
+                            write_handle.force_get_current_song = true;
+                        }
+                        PollState::Stats => {
+                            write_handle.dirty_flag.store(true, Ordering::Release);
+                        }
+                        PollState::ReadPicture if write_handle.art_data.is_empty() => {
+                            write_handle.can_get_album_art = false;
+                            write_handle.dirty_flag.store(true, Ordering::Release);
+                            log(
+                                "No embedded album art",
+                                LogState::Warning,
+                                write_handle.log_level,
+                            );
                         }
                         PollState::ReadPictureInDir if write_handle.art_data.is_empty() => {
                             write_handle.can_get_album_art_in_dir = false;
@@ -654,26 +865,41 @@ impl MPDHandler {
                                 write_handle.error_text.push_str(" (not authenticated?)");
                             }
                         }
-                        PollState::ReadPicture => {
-                            write_handle.can_get_album_art = false;
+                        PollState::ReadPicture | PollState::ReadPictureInDir => {
+                            if write_handle.poll_state == PollState::ReadPicture {
+                                write_handle.can_get_album_art = false;
+                                log(
+                                    "Failed to get readpicture",
+                                    LogState::Warning,
+                                    write_handle.log_level,
+                                );
+                            } else {
+                                write_handle.can_get_album_art_in_dir = false;
+                                log(
+                                    "Failed to get albumart",
+                                    LogState::Warning,
+                                    write_handle.log_level,
+                                );
+                            }
                             write_handle.dirty_flag.store(true, Ordering::Release);
-                            log(
-                                "Failed to get readpicture",
-                                LogState::Warning,
-                                write_handle.log_level,
-                            );
-                            // Not setting error_text here since
-                            // ReadPictureInDir is tried next
+                            // Not setting error_text while there is another
+                            // source to try through MPD
+                            if write_handle.get_art_source().is_none() {
+                                write_handle.error_text = "Failed to get album art from MPD".into();
+                            }
                         }
-                        PollState::ReadPictureInDir => {
-                            write_handle.can_get_album_art_in_dir = false;
-                            write_handle.dirty_flag.store(true, Ordering::Release);
+                        PollState::Stats => {
+                            write_handle.can_get_stats = false;
+                        }
+                        PollState::Config => {
                             log(
-                                "Failed to get albumart",
+                                "Failed to get the music directory from MPD",
                                 LogState::Warning,
                                 write_handle.log_level,
                             );
-                            write_handle.error_text = "Failed to get album art from MPD".into();
+                        }
+                        PollState::Command => {
+                            write_handle.force_get_status = true;
                         }
                         _ => (),
                     }
@@ -686,30 +912,33 @@ impl MPDHandler {
                         write_handle.art_data.clear();
                         write_handle.art_data_size = 0;
                         write_handle.art_data_type.clear();
-                        write_handle.can_get_album_art = true;
-                        write_handle.can_get_album_art_in_dir = true;
+                        write_handle.reset_art_sources();
                         write_handle.current_song_title.clear();
                         write_handle.current_song_artist.clear();
                         write_handle.current_song_album.clear();
+                        write_handle.current_song_tags.clear();
                         write_handle.current_song_length = 0.0;
                         write_handle.current_song_position = 0.0;
                         write_handle.did_check_overtime = false;
                         write_handle.force_get_status = true;
                     }
                     if remaining == "stop" || remaining == "pause" {
-                        got_mpd_state = if remaining == "stop" {
+                        let state = if remaining == "stop" {
                             MPDPlayState::Stopped
                         } else {
                             MPDPlayState::Paused
                         };
+                        got_mpd_state = Some(state);
                         write_handle.error_text.clear();
-                        write!(&mut write_handle.error_text, "MPD has {got_mpd_state:?}").ok();
+                        write!(&mut write_handle.error_text, "MPD has {state:?}").ok();
                         log(
-                            format!("MPD is {got_mpd_state:?}"),
+                            format!("MPD is {state:?}"),
                             LogState::Warning,
                             write_handle.log_level,
                         );
                         break 'handle_buf;
+                    } else if remaining == "play" {
+                        got_mpd_state = Some(MPDPlayState::Playing);
                     }
                 } else if line.starts_with("file: ") {
                     let song_file = line.split_off(6);
@@ -718,11 +947,11 @@ impl MPDHandler {
                         write_handle.art_data.clear();
                         write_handle.art_data_size = 0;
                         write_handle.art_data_type.clear();
-                        write_handle.can_get_album_art = true;
-                        write_handle.can_get_album_art_in_dir = true;
+                        write_handle.reset_art_sources();
                         write_handle.current_song_title.clear();
                         write_handle.current_song_artist.clear();
                         write_handle.current_song_album.clear();
+                        write_handle.current_song_tags.clear();
                         write_handle.current_song_length = 0.0;
                         write_handle.current_song_position = 0.0;
                         write_handle.did_check_overtime = false;
@@ -757,6 +986,20 @@ impl MPDHandler {
                             write_handle.log_level,
                         );
                     }
+                } else if line.starts_with("volume: ") {
+                    let parse_volume_result = i32::from_str(&line.split_off(8));
+                    if let Ok(value) = parse_volume_result {
+                        if value != write_handle.current_volume {
+                            write_handle.current_volume = value;
+                            write_handle.dirty_flag.store(true, Ordering::Release);
+                        }
+                    } else {
+                        log(
+                            "Failed to parse current volume",
+                            LogState::Warning,
+                            write_handle.log_level,
+                        );
+                    }
                 } else if line.starts_with("size: ") {
                     let parse_artsize_result = usize::from_str(&line.split_off(6));
                     if let Ok(value) = parse_artsize_result {
@@ -788,6 +1031,34 @@ impl MPDHandler {
                     write_handle.current_song_album = line.split_off(7);
                 } else if line.starts_with("type: ") {
                     write_handle.art_data_type = line.split_off(6);
+                } else if write_handle.poll_state == PollState::Stats
+                    && let Some((key, value)) = line.split_once(": ")
+                {
+                    let value = u64::from_str(value).unwrap_or_default();
+                    let stats = write_handle.stats.get_or_insert_default();
+                    match key {
+                        "artists" => stats.artists = value,
+                        "albums" => stats.albums = value,
+                        "songs" => stats.songs = value,
+                        "uptime" => stats.uptime = value,
+                        _ => (),
+                    }
+                } else if write_handle.poll_state == PollState::Config {
+                    if let Some(music_dir) = line.strip_prefix("music_directory: ") {
+                        log(
+                            format!("Got music directory {music_dir} from MPD"),
+                            LogState::Debug,
+                            write_handle.log_level,
+                        );
+                        write_handle.music_dir = Some(PathBuf::from(music_dir));
+                        write_handle.dirty_flag.store(true, Ordering::Release);
+                    }
+                } else if line.starts_with(|c: char| c.is_ascii_uppercase())
+                    && let Some((key, value)) = line.split_once(": ")
+                {
+                    // Other tags of the current song, "status" uses lowercase keys
+                    let key = key.to_lowercase();
+                    write_handle.current_song_tags.insert(key, value.to_owned());
                 } else {
                     log(
                         format!("Got unrecognized/ignored line: {line}"),
@@ -813,18 +1084,22 @@ impl MPDHandler {
             }
         } // 'handle_buf: loop
 
-        if got_mpd_state != write_handle.mpd_play_state {
-            write_handle.dirty_flag.store(true, Ordering::Release);
-            if got_mpd_state == MPDPlayState::Playing {
-                write_handle.error_text.clear();
+        // Only a "status" reply carries the play state, so replies to other
+        // commands leave the previously known state as is.
+        if let Some(got_mpd_state) = got_mpd_state {
+            if got_mpd_state != write_handle.mpd_play_state {
+                write_handle.dirty_flag.store(true, Ordering::Release);
+                if got_mpd_state == MPDPlayState::Playing {
+                    write_handle.error_text.clear();
+                }
+            }
+            write_handle.mpd_play_state = got_mpd_state;
+            if got_mpd_state != MPDPlayState::Playing {
+                write_handle.poll_state = PollState::None;
+                write_handle.song_pos_get_time = Instant::now();
+                write_handle.current_song_length = 30.0;
+                write_handle.current_song_position = 0.0;
             }
-        }
-        write_handle.mpd_play_state = got_mpd_state;
-        if got_mpd_state != MPDPlayState::Playing {
-            write_handle.poll_state = PollState::None;
-            write_handle.song_pos_get_time = Instant::now();
-            write_handle.current_song_length = 30.0;
-            write_handle.current_song_position = 0.0;
         }
 
         Ok(())
@@ -863,6 +1138,33 @@ impl MPDHandler {
                         write_handle.log_level,
                     );
                 }
+            } else if write_handle.is_authenticated && write_handle.can_get_config {
+                write_handle.can_get_config = false;
+                let write_result = write_handle.stream.write(b"config\n");
+                if write_result.is_ok() {
+                    write_handle.poll_state = PollState::Config;
+                } else if let Err(e) = write_result {
+                    log(
+                        format!("Failed to request the music directory: {e}"),
+                        LogState::Error,
+                        write_handle.log_level,
+                    );
+                }
+            } else if write_handle.is_authenticated
+                && let Some(command) = write_handle.pending_commands.pop_front()
+            {
+                let write_result = write_handle
+                    .stream
+                    .write(command.to_protocol_string().as_bytes());
+                if write_result.is_ok() {
+                    write_handle.poll_state = PollState::Command;
+                } else if let Err(e) = write_result {
+                    log(
+                        format!("Failed to send command {command:?}: {e}"),
+                        LogState::Error,
+                        write_handle.log_level,
+                    );
+                }
             } else if write_handle.can_get_status
                 && (write_handle.song_title_get_time.elapsed() > POLL_DURATION
                     || write_handle.force_get_current_song)
@@ -895,38 +1197,57 @@ impl MPDHandler {
                         write_handle.log_level,
                     );
                 }
+            } else if write_handle.can_get_stats
+                && write_handle.mpd_play_state != MPDPlayState::Playing
+                && write_handle.stats_get_time.elapsed() > POLL_DURATION
+            {
+                write_handle.stats_get_time = Instant::now();
+                let write_result = write_handle.stream.write(b"stats\n");
+                if write_result.is_ok() {
+                    write_handle.poll_state = PollState::Stats;
+                } else if let Err(e) = write_result {
+                    log(
+                        format!("Failed to request stats over stream: {e}"),
+                        LogState::Error,
+                        write_handle.log_level,
+                    );
+                }
             } else if (write_handle.art_data.is_empty()
                 || write_handle.art_data.len() != write_handle.art_data_size)
                 && !write_handle.current_song_filename.is_empty()
             {
                 let title = write_handle.current_song_filename.clone();
                 let art_data_length = write_handle.art_data.len();
-                if write_handle.can_get_album_art {
-                    let write_result = write_handle
-                        .stream
-                        .write(format!("readpicture \"{title}\" {art_data_length}\n").as_bytes());
-                    if write_result.is_ok() {
-                        write_handle.poll_state = PollState::ReadPicture;
-                    } else if let Err(e) = write_result {
-                        log(
-                            format!("Failed to request album art: {e}"),
-                            LogState::Error,
-                            write_handle.log_level,
+                match write_handle.get_art_source() {
+                    Some(ArtSource::Embedded) => {
+                        let write_result = write_handle.stream.write(
+                            format!("readpicture \"{title}\" {art_data_length}\n").as_bytes(),
                         );
+                        if write_result.is_ok() {
+                            write_handle.poll_state = PollState::ReadPicture;
+                        } else if let Err(e) = write_result {
+                            log(
+                                format!("Failed to request album art: {e}"),
+                                LogState::Error,
+                                write_handle.log_level,
+                            );
+                        }
                     }
-                } else if write_handle.can_get_album_art_in_dir {
-                    let write_result = write_handle
-                        .stream
-                        .write(format!("albumart \"{title}\" {art_data_length}\n").as_bytes());
-                    if write_result.is_ok() {
-                        write_handle.poll_state = PollState::ReadPictureInDir;
-                    } else if let Err(e) = write_result {
-                        log(
-                            format!("Failed to request album art in dir: {e}"),
-                            LogState::Error,
-                            write_handle.log_level,
-                        );
+                    Some(ArtSource::MpdFolder) => {
+                        let write_result = write_handle
+                            .stream
+                            .write(format!("albumart \"{title}\" {art_data_length}\n").as_bytes());
+                        if write_result.is_ok() {
+                            write_handle.poll_state = PollState::ReadPictureInDir;
+                        } else if let Err(e) = write_result {
+                            log(
+                                format!("Failed to request album art in dir: {e}"),
+                                LogState::Error,
+                                write_handle.log_level,
+                            );
+                        }
                     }
+                    _ => (),
                 }
             }
         }
@@ -951,6 +1272,24 @@ impl MPDHandlerState {
         self.art_data_type.clone()
     }
 
+    /// The first source fetched through MPD that has not failed yet, which
+    /// is the one any album art data held came from.
+    pub fn get_art_source(&self) -> Option<ArtSource> {
+        self.art_sources
+            .iter()
+            .copied()
+            .find(|source| match source {
+                ArtSource::Embedded => self.can_get_album_art,
+                ArtSource::MpdFolder => self.can_get_album_art_in_dir,
+                _ => false,
+            })
+    }
+
+    fn reset_art_sources(&mut self) {
+        self.can_get_album_art = self.art_sources.contains(&ArtSource::Embedded);
+        self.can_get_album_art_in_dir = self.art_sources.contains(&ArtSource::MpdFolder);
+    }
+
     pub fn is_art_data_ready(&self) -> bool {
         log(
             format!(
@@ -0,0 +1,279 @@
+use crate::config::{RowTheme, Theme};
+use ggez::graphics::Color;
+use image::DynamicImage;
+
+/// WCAG AA contrast ratio for normal sized text.
+pub const MIN_CONTRAST: f32 = 4.5;
+const SAMPLE_SIZE: u32 = 64;
+const MAX_BOXES: usize = 8;
+/// Colors used by less than this fraction of the pixels are not used as the
+/// accent, so that a few stray pixels can't pick it.
+const MIN_ACCENT_SHARE: f32 = 0.03;
+const BACKGROUND_BRIGHTNESS: f32 = 0.5;
+const PLATE_BRIGHTNESS: f32 = 0.35;
+
+type Rgb = [u8; 3];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub dominant: Rgb,
+    pub accent: Rgb,
+}
+
+struct ColorBox {
+    pixels: Vec<Rgb>,
+}
+
+impl ColorBox {
+    /// Returns the channel with the widest range and that range.
+    fn widest_channel(&self) -> (usize, u8) {
+        (0..3)
+            .map(|channel| {
+                let (min, max) = self
+                    .pixels
+                    .iter()
+                    .fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+                        (min.min(pixel[channel]), max.max(pixel[channel]))
+                    });
+                (channel, max.saturating_sub(min))
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap()
+    }
+
+    fn average(&self) -> Rgb {
+        let mut sum = [0u64; 3];
+        for pixel in &self.pixels {
+            for channel in 0..3 {
+                sum[channel] += pixel[channel] as u64;
+            }
+        }
+        let count = self.pixels.len().max(1) as u64;
+        sum.map(|channel| (channel / count) as u8)
+    }
+}
+
+/// Finds the most common color and the color that stands out the most from
+/// it with median cut.
+pub fn extract_palette(img: &DynamicImage) -> Option<Palette> {
+    let sample = img.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgba8();
+    let pixels: Vec<Rgb> = sample
+        .pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let total = pixels.len();
+    if total == 0 {
+        return None;
+    }
+
+    let mut boxes = vec![ColorBox { pixels }];
+    while boxes.len() < MAX_BOXES {
+        let Some((idx, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, color_box)| color_box.pixels.len() > 1)
+            .map(|(idx, color_box)| {
+                let (channel, range) = color_box.widest_channel();
+                (idx, channel, range as usize * color_box.pixels.len())
+            })
+            .filter(|(_, _, score)| *score > 0)
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(idx, channel, _)| (idx, channel))
+        else {
+            break;
+        };
+        let mut pixels = std::mem::take(&mut boxes[idx].pixels);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes[idx].pixels = pixels;
+        boxes.push(ColorBox { pixels: upper });
+    }
+
+    let colors: Vec<(Rgb, usize)> = boxes
+        .iter()
+        .map(|color_box| (color_box.average(), color_box.pixels.len()))
+        .collect();
+    let dominant = colors
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(color, _)| *color)?;
+    let accent = colors
+        .iter()
+        .filter(|(_, count)| *count as f32 >= total as f32 * MIN_ACCENT_SHARE)
+        .map(|(color, _)| *color)
+        .max_by(|a, b| accent_score(*a, dominant).total_cmp(&accent_score(*b, dominant)))
+        .unwrap_or(dominant);
+
+    Some(Palette { dominant, accent })
+}
+
+fn accent_score(color: Rgb, dominant: Rgb) -> f32 {
+    let distance = (0..3)
+        .map(|channel| (color[channel] as f32 - dominant[channel] as f32).powi(2))
+        .sum::<f32>()
+        .sqrt();
+    distance * (0.5 + saturation(color))
+}
+
+fn saturation(color: Rgb) -> f32 {
+    let max = *color.iter().max().unwrap() as f32;
+    let min = *color.iter().min().unwrap() as f32;
+    if max == 0.0 { 0.0 } else { (max - min) / max }
+}
+
+fn relative_luminance(color: Rgb) -> f32 {
+    let linear = color.map(|channel| {
+        let channel = channel as f32 / 255.0;
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2]
+}
+
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn mix(a: Rgb, b: Rgb, amount: f32) -> Rgb {
+    [0, 1, 2].map(|channel| {
+        (a[channel] as f32 + (b[channel] as f32 - a[channel] as f32) * amount).round() as u8
+    })
+}
+
+fn scale(color: Rgb, brightness: f32) -> Rgb {
+    color.map(|channel| (channel as f32 * brightness).round() as u8)
+}
+
+/// The lowest contrast ratio of the color with any of `against`.
+fn min_contrast_ratio(color: Rgb, against: &[Rgb]) -> f32 {
+    against
+        .iter()
+        .map(|against| contrast_ratio(color, *against))
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Moves the color towards white or black, whichever contrasts more with all
+/// of `against`, until its contrast ratio with each is at least `min_ratio`.
+pub fn ensure_contrast(color: Rgb, against: &[Rgb], min_ratio: f32) -> Rgb {
+    if min_contrast_ratio(color, against) >= min_ratio {
+        return color;
+    }
+    let target = if min_contrast_ratio([255; 3], against) >= min_contrast_ratio([0; 3], against) {
+        [255; 3]
+    } else {
+        [0; 3]
+    };
+    for step in 1..=20 {
+        let mixed = mix(color, target, step as f32 / 20.0);
+        if min_contrast_ratio(mixed, against) >= min_ratio {
+            return mixed;
+        }
+    }
+    target
+}
+
+fn to_color(color: Rgb, alpha: f32) -> Color {
+    let mut color = Color::from_rgb(color[0], color[1], color[2]);
+    color.a = alpha;
+    color
+}
+
+impl Palette {
+    /// Replaces the colors of the theme, keeping its opacities, paddings and
+    /// fonts.
+    pub fn apply_to(&self, theme: &Theme) -> Theme {
+        // Darkened where needed so that white text can always reach the
+        // minimum contrast on both, as the text is checked against each
+        let background = ensure_contrast(
+            scale(self.dominant, BACKGROUND_BRIGHTNESS),
+            &[[255; 3]],
+            MIN_CONTRAST,
+        );
+        let plate = ensure_contrast(
+            scale(self.dominant, PLATE_BRIGHTNESS),
+            &[[255; 3]],
+            MIN_CONTRAST,
+        );
+        let apply_row = |row: &RowTheme| {
+            // rows drawn without a plate sit straight on the background, the
+            // others on the plate blended over it at the row's opacity
+            let shown_plate = mix(background, plate, row.text_bg_color.a);
+            let text = ensure_contrast(self.accent, &[background, shown_plate], MIN_CONTRAST);
+            RowTheme {
+                text_color: to_color(text, row.text_color.a),
+                text_bg_color: to_color(plate, row.text_bg_color.a),
+                ..row.clone()
+            }
+        };
+        Theme {
+            background_color: to_color(background, 1.0),
+            default: apply_row(&theme.default),
+            title: apply_row(&theme.title),
+            artist: apply_row(&theme.artist),
+            album: apply_row(&theme.album),
+            filename: apply_row(&theme.filename),
+            timer: apply_row(&theme.timer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_extract_palette() {
+        let img = RgbaImage::from_fn(100, 100, |x, _| {
+            if x < 80 {
+                Rgba([20, 30, 120, 255])
+            } else {
+                Rgba([240, 200, 20, 255])
+            }
+        });
+        let palette = extract_palette(&DynamicImage::ImageRgba8(img)).unwrap();
+        assert_eq!(palette.dominant, [20, 30, 120]);
+        assert_eq!(palette.accent, [240, 200, 20]);
+    }
+
+    #[test]
+    fn test_ensure_contrast() {
+        assert!((contrast_ratio([0; 3], [255; 3]) - 21.0).abs() < 0.01);
+
+        let plate = [40, 40, 50];
+        let text = ensure_contrast([60, 60, 70], &[plate], MIN_CONTRAST);
+        assert!(contrast_ratio(text, plate) >= MIN_CONTRAST);
+
+        let plate = [230, 230, 200];
+        let text = ensure_contrast([200, 200, 180], &[plate], MIN_CONTRAST);
+        assert!(contrast_ratio(text, plate) >= MIN_CONTRAST);
+    }
+
+    #[test]
+    fn test_bright_dominant_contrast() {
+        let palette = Palette {
+            dominant: [255; 3],
+            accent: [200, 200, 210],
+        };
+        let mut theme = Theme::default();
+        theme.title.text_bg_color.a = 0.5;
+        theme.artist.text_bg_color.a = 1.0;
+        let theme = palette.apply_to(&theme);
+        let rgb = |color: Color| {
+            let (r, g, b) = color.to_rgb();
+            [r, g, b]
+        };
+        let background = rgb(theme.background_color);
+        for row in [&theme.title, &theme.artist] {
+            let text = rgb(row.text_color);
+            let shown_plate = mix(background, rgb(row.text_bg_color), row.text_bg_color.a);
+            assert!(contrast_ratio(text, background) >= MIN_CONTRAST);
+            assert!(contrast_ratio(text, shown_plate) >= MIN_CONTRAST);
+        }
+    }
+}
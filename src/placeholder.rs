@@ -0,0 +1,191 @@
+use image::{Rgba, RgbaImage};
+
+pub const PLACEHOLDER_SIZE: u32 = 512;
+/// Height of the initials relative to the placeholder size.
+const INITIALS_HEIGHT_RATIO: f32 = 0.3;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// A 5x7 pixel font for the initials, one row per byte with the leftmost
+/// pixel in bit 4, so that no font file has to be shipped.
+fn glyph(c: char) -> Option<[u8; 7]> {
+    let rows = match c {
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// FNV-1a, as the hashers in std are not guaranteed to give the same result
+/// across releases.
+fn hash(string: &str) -> u64 {
+    string.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The first letter or digit, if the built in font has it.
+fn initial(string: &str) -> Option<char> {
+    string
+        .chars()
+        .find(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .filter(|c| glyph(*c).is_some())
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [f32; 3] {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r + m, g + m, b + m]
+}
+
+/// Generates art for songs without any, seeded by the album and artist so
+/// that every album gets its own colors and pattern.
+pub fn generate(album: &str, artist: &str) -> RgbaImage {
+    let seed = hash(&format!("{artist}\n{album}"));
+    let bits = |shift: u32, count: u32| (seed >> shift) & ((1 << count) - 1);
+
+    let hue = bits(0, 9) as f32 / 512.0 * 360.0;
+    let hue_offset = 60.0 + bits(9, 7) as f32 / 128.0 * 120.0;
+    let start = hsv_to_rgb(hue, 0.55, 0.6);
+    let end = hsv_to_rgb(hue + hue_offset, 0.65, 0.3);
+    let angle = bits(16, 8) as f32 / 256.0 * std::f32::consts::TAU;
+    let (dir_x, dir_y) = (angle.cos(), angle.sin());
+    let pattern = bits(24, 8) % 3;
+    let spacing = PLACEHOLDER_SIZE as f32 / (6 + bits(32, 3)) as f32;
+
+    let size = PLACEHOLDER_SIZE as f32;
+    let mut img = RgbaImage::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+        let (fx, fy) = (x as f32 / size - 0.5, y as f32 / size - 0.5);
+        // 0.0 to 1.0 along the gradient direction
+        let t = ((fx * dir_x + fy * dir_y) / std::f32::consts::SQRT_2 + 0.5).clamp(0.0, 1.0);
+        let (px, py) = (x as f32, y as f32);
+        let is_shaded = match pattern {
+            // diagonal stripes
+            0 => (((px + py) / spacing) as u32).is_multiple_of(2),
+            // rings around the center
+            1 => ((fx.hypot(fy) * size / spacing) as u32).is_multiple_of(2),
+            // a grid of dots
+            _ => {
+                let dx = px % spacing - spacing / 2.0;
+                let dy = py % spacing - spacing / 2.0;
+                dx.hypot(dy) < spacing / 4.0
+            }
+        };
+        let shade = if is_shaded { 1.12 } else { 1.0 };
+        let channel = |idx: usize| {
+            ((start[idx] + (end[idx] - start[idx]) * t) * shade * 255.0).clamp(0.0, 255.0) as u8
+        };
+        Rgba([channel(0), channel(1), channel(2), 255])
+    });
+
+    let initials: Vec<char> = [initial(album), initial(artist)]
+        .into_iter()
+        .flatten()
+        .collect();
+    draw_initials(&mut img, &initials);
+    img
+}
+
+fn draw_initials(img: &mut RgbaImage, initials: &[char]) {
+    if initials.is_empty() {
+        return;
+    }
+    let scale = (PLACEHOLDER_SIZE as f32 * INITIALS_HEIGHT_RATIO / GLYPH_HEIGHT as f32) as u32;
+    // one pixel of space between the glyphs
+    let width = (initials.len() as u32 * (GLYPH_WIDTH + 1) - 1) * scale;
+    let left = (PLACEHOLDER_SIZE - width) / 2;
+    let top = (PLACEHOLDER_SIZE - GLYPH_HEIGHT * scale) / 2;
+    for (idx, c) in initials.iter().enumerate() {
+        let Some(rows) = glyph(*c) else {
+            continue;
+        };
+        let glyph_left = left + idx as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row_idx, row) in rows.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if row & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                for y in 0..scale {
+                    for x in 0..scale {
+                        let pixel = img.get_pixel_mut(
+                            glyph_left + column * scale + x,
+                            top + row_idx as u32 * scale + y,
+                        );
+                        // mostly white, letting a bit of the pattern through
+                        for channel in &mut pixel.0[..3] {
+                            *channel = (*channel as u32 * 15 / 100 + 255 * 85 / 100) as u8;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(initial("the Beatles"), Some('T'));
+        assert_eq!(initial("(What's the Story)"), Some('W'));
+        assert_eq!(initial("1989"), Some('1'));
+        assert_eq!(initial("..."), None);
+        assert_eq!(initial("東京"), None);
+    }
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let img = generate("Abbey Road", "The Beatles");
+        assert_eq!(img.dimensions(), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+        assert_eq!(img, generate("Abbey Road", "The Beatles"));
+        assert_ne!(img, generate("Let It Be", "The Beatles"));
+    }
+}
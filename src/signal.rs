@@ -1,10 +1,13 @@
 use std::sync::atomic::AtomicBool;
 
 pub static TO_CLOSE_REQUESTED: AtomicBool = AtomicBool::new(false);
+pub static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
 
 #[cfg(target_family = "unix")]
 extern "C" fn handle_signal(sig: std::ffi::c_int) {
-    if sig == libc::SIGHUP || sig == libc::SIGINT || sig == libc::SIGTERM {
+    if sig == libc::SIGHUP {
+        RELOAD_REQUESTED.store(true, std::sync::atomic::Ordering::Relaxed);
+    } else if sig == libc::SIGINT || sig == libc::SIGTERM {
         TO_CLOSE_REQUESTED.store(true, std::sync::atomic::Ordering::Relaxed);
     }
 }
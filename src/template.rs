@@ -0,0 +1,170 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A format string for a text row, like `"{artist}[ - {album}][ ({date})]"`.
+///
+/// `{name}` is replaced with the value of the field of that name, and a
+/// section in square brackets is left out entirely if any field in it is
+/// missing or empty. A backslash escapes the character after it.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(String),
+    Section(Vec<Part>),
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut chars = template.chars();
+        let parts = parse_parts(&mut chars, false)?;
+        Ok(Self { parts })
+    }
+
+    /// A template that only shows the given field.
+    pub fn field(name: &str) -> Self {
+        Self {
+            parts: vec![Part::Field(name.to_owned())],
+        }
+    }
+
+    pub fn render<'a, F>(&self, lookup: F) -> String
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        let mut output = String::new();
+        render_parts(&self.parts, &lookup, &mut output);
+        output
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+fn parse_parts(chars: &mut std::str::Chars, is_section: bool) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    loop {
+        let Some(c) = chars.next() else {
+            if is_section {
+                return Err(String::from("unclosed \"[\" in template"));
+            }
+            break;
+        };
+        match c {
+            '\\' => literal.push(
+                chars
+                    .next()
+                    .ok_or_else(|| String::from("template ends with \"\\\""))?,
+            ),
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(String::from("unclosed \"{\" in template")),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(String::from("empty \"{}\" in template"));
+                }
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Field(name.to_lowercase()));
+            }
+            '[' => {
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Section(parse_parts(chars, true)?));
+            }
+            ']' if is_section => break,
+            '}' | ']' => return Err(format!("unexpected \"{c}\" in template")),
+            _ => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(parts)
+}
+
+/// Returns false if a field is missing, in which case the enclosing section
+/// is dropped.
+fn render_parts<'a, F>(parts: &[Part], lookup: &F, output: &mut String) -> bool
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    let mut is_complete = true;
+    for part in parts {
+        match part {
+            Part::Literal(literal) => output.push_str(literal),
+            Part::Field(name) => match lookup(name) {
+                Some(value) if !value.is_empty() => output.push_str(value),
+                _ => is_complete = false,
+            },
+            Part::Section(section) => {
+                let mut section_output = String::new();
+                if render_parts(section, lookup, &mut section_output) {
+                    output.push_str(&section_output);
+                }
+            }
+        }
+    }
+    is_complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<&'static str> {
+        match name {
+            "artist" => Some("Artist"),
+            "album" => Some("Album"),
+            "track" => Some("3"),
+            "date" => Some(""),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_sections() {
+        let template = Template::parse("{artist}[ - {album}][ ({date})]").unwrap();
+        assert_eq!(template.render(lookup), "Artist - Album");
+
+        let template = Template::parse("[{track}[/{totaltracks}] ]{Title}").unwrap();
+        assert_eq!(template.render(lookup), "3 ");
+
+        let template = Template::parse("\\[{artist}\\]").unwrap();
+        assert_eq!(template.render(lookup), "[Artist]");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::parse("{artist").is_err());
+        assert!(Template::parse("[{artist}").is_err());
+        assert!(Template::parse("{artist}]").is_err());
+        assert!(Template::parse("{}").is_err());
+    }
+}
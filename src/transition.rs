@@ -0,0 +1,106 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[derive(ValueEnum, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionKind {
+    None,
+    Crossfade,
+    Slide,
+    FadeBlack,
+}
+
+/// How to draw the outgoing and incoming content at some point of a
+/// transition. Offsets are fractions of the window width.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TransitionFrame {
+    pub old_alpha: f32,
+    pub new_alpha: f32,
+    pub old_offset: f32,
+    pub new_offset: f32,
+}
+
+impl TransitionFrame {
+    /// Used when not transitioning.
+    pub const DONE: TransitionFrame = TransitionFrame {
+        old_alpha: 0.0,
+        new_alpha: 1.0,
+        old_offset: 0.0,
+        new_offset: 0.0,
+    };
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Transition {
+    kind: TransitionKind,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Transition {
+    /// Returns None if the change should happen immediately.
+    pub fn start(kind: TransitionKind, duration: f32) -> Option<Self> {
+        if kind == TransitionKind::None || duration <= 0.0 {
+            None
+        } else {
+            Some(Self {
+                kind,
+                duration,
+                elapsed: 0.0,
+            })
+        }
+    }
+
+    /// Returns false once the transition is over.
+    pub fn advance(&mut self, delta: f32) -> bool {
+        self.elapsed += delta;
+        self.elapsed < self.duration
+    }
+
+    pub fn frame(&self) -> TransitionFrame {
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        // smoothstep
+        let p = t * t * (3.0 - 2.0 * t);
+        match self.kind {
+            TransitionKind::None => TransitionFrame::DONE,
+            TransitionKind::Crossfade => TransitionFrame {
+                old_alpha: 1.0 - p,
+                new_alpha: p,
+                ..TransitionFrame::DONE
+            },
+            TransitionKind::Slide => TransitionFrame {
+                old_alpha: 1.0,
+                new_alpha: 1.0,
+                old_offset: -p,
+                new_offset: 1.0 - p,
+            },
+            TransitionKind::FadeBlack => TransitionFrame {
+                old_alpha: (1.0 - 2.0 * p).max(0.0),
+                new_alpha: (2.0 * p - 1.0).max(0.0),
+                ..TransitionFrame::DONE
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition_frames() {
+        assert!(Transition::start(TransitionKind::None, 1.0).is_none());
+
+        let mut transition = Transition::start(TransitionKind::FadeBlack, 0.1).unwrap();
+        assert_eq!(transition.frame().old_alpha, 1.0);
+        assert_eq!(transition.frame().new_alpha, 0.0);
+        assert!(transition.advance(0.03));
+        let frame = transition.frame();
+        assert!(frame.old_alpha > 0.0 && frame.new_alpha == 0.0);
+        assert!(transition.advance(0.04));
+        let frame = transition.frame();
+        assert!(frame.old_alpha == 0.0 && frame.new_alpha > 0.0);
+        while transition.advance(0.04) {}
+        assert_eq!(transition.frame(), TransitionFrame::DONE);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::Opt;
+use crate::debug_log::log_warning;
+use ggez::Context;
+use ggez::conf::FullscreenType;
+use ggez::winit::dpi::{PhysicalPosition, PhysicalSize};
+use ggez::winit::window::{Icon, WindowLevel};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The window title when nothing is playing.
+pub const DEFAULT_TITLE: &str = "mpd info screen";
+/// The largest side of the window icon made from the album art.
+pub const ICON_SIZE: u32 = 64;
+const STATE_DIR_NAME: &str = "mpd_info_screen";
+const WINDOW_STATE_FILE_NAME: &str = "window.toml";
+
+/// A window size with an optional position, like X11's "WxH+X+Y".
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Geometry {
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<(i32, i32)>,
+}
+
+impl FromStr for Geometry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid geometry \"{s}\", expected WxH or WxH+X+Y");
+        let (size, position) = match s.find(['+', '-']) {
+            Some(idx) => (&s[..idx], Some(&s[idx..])),
+            None => (s, None),
+        };
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: u32 = width.parse().map_err(|_| invalid())?;
+        let height: u32 = height.parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        let position = position
+            .map(|position| {
+                // the sign of each offset is part of it, as in "+10-20"
+                let idx = position[1..]
+                    .find(['+', '-'])
+                    .map(|idx| idx + 1)
+                    .ok_or_else(invalid)?;
+                let x: i32 = position[..idx].parse().map_err(|_| invalid())?;
+                let y: i32 = position[idx..].parse().map_err(|_| invalid())?;
+                Ok::<_, String>((x, y))
+            })
+            .transpose()?;
+        Ok(Self {
+            width,
+            height,
+            position,
+        })
+    }
+}
+
+impl TryFrom<String> for Geometry {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// What is remembered of the window between runs.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct WindowState {
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+}
+
+fn window_state_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(state_dir.join(STATE_DIR_NAME).join(WINDOW_STATE_FILE_NAME))
+}
+
+/// The geometry of the window when the last run exited, if there was one.
+pub fn load_saved_geometry() -> Option<Geometry> {
+    let content = std::fs::read_to_string(window_state_path()?).ok()?;
+    let state: WindowState = toml::from_str(&content)
+        .map_err(|e| log_warning(format!("Ignoring invalid window state: {e}")))
+        .ok()?;
+    Some(Geometry {
+        width: state.width.max(1),
+        height: state.height.max(1),
+        position: Some((state.x, state.y)),
+    })
+}
+
+/// Remembers the size and position of the window for the next run, unless
+/// it is fullscreen.
+pub fn save_geometry(ctx: &Context) -> Result<(), String> {
+    let window = ctx.gfx.window();
+    if window.fullscreen().is_some() {
+        return Ok(());
+    }
+    let size = window.inner_size();
+    let position = ctx
+        .gfx
+        .window_position()
+        .map_err(|e| format!("Failed to get the window position: {e}"))?;
+    let state = WindowState {
+        width: size.width,
+        height: size.height,
+        x: position.x,
+        y: position.y,
+    };
+    let path = window_state_path().ok_or("Failed to find the state directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let content = toml::to_string(&state).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Places the window on the chosen monitor and applies the options that
+/// can only be set once the window exists.
+pub fn setup(ctx: &mut Context, opt: &Opt, geometry: Option<Geometry>) {
+    let monitor = opt.monitor.and_then(|idx| {
+        let monitor = ctx.gfx.window().available_monitors().nth(idx);
+        if monitor.is_none() {
+            log_warning(format!("Monitor {idx} not found, using the default one"));
+        }
+        monitor
+    });
+    let position = match (&monitor, geometry.and_then(|geometry| geometry.position)) {
+        // relative to the monitor
+        (Some(monitor), Some((x, y))) => Some(PhysicalPosition::new(
+            monitor.position().x + x,
+            monitor.position().y + y,
+        )),
+        // centered on the monitor
+        (Some(monitor), None) => {
+            let PhysicalSize { width, height } = ctx.gfx.window().outer_size();
+            Some(PhysicalPosition::new(
+                monitor.position().x + (monitor.size().width as i32 - width as i32) / 2,
+                monitor.position().y + (monitor.size().height as i32 - height as i32) / 2,
+            ))
+        }
+        (None, Some((x, y))) => Some(PhysicalPosition::new(x, y)),
+        (None, None) => None,
+    };
+    if let Some(position) = position
+        && let Err(e) = ctx.gfx.set_window_position(position)
+    {
+        log_warning(format!("Failed to move the window: {e}"));
+    }
+
+    if opt.always_on_top {
+        ctx.gfx.window().set_window_level(WindowLevel::AlwaysOnTop);
+    }
+    // fills the monitor the window was moved to
+    if opt.fullscreen
+        && let Err(e) = ctx.gfx.set_fullscreen(FullscreenType::Desktop)
+    {
+        log_warning(format!("Failed to go fullscreen: {e}"));
+    }
+}
+
+pub fn toggle_fullscreen(ctx: &mut Context) -> Result<(), String> {
+    let fullscreen_type = if ctx.gfx.window().fullscreen().is_some() {
+        FullscreenType::Windowed
+    } else {
+        FullscreenType::Desktop
+    };
+    ctx.gfx
+        .set_fullscreen(fullscreen_type)
+        .map_err(|e| format!("Failed to toggle fullscreen: {e}"))
+}
+
+/// Shows the album art as the window icon, or the default icon for None.
+pub fn set_icon(ctx: &Context, icon: Option<&RgbaImage>) -> Result<(), String> {
+    let icon = icon
+        .map(|icon| Icon::from_rgba(icon.as_raw().clone(), icon.width(), icon.height()))
+        .transpose()
+        .map_err(|e| format!("Failed to make the window icon: {e}"))?;
+    ctx.gfx.window().set_window_icon(icon);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry() {
+        assert_eq!(
+            "800x480".parse(),
+            Ok(Geometry {
+                width: 800,
+                height: 480,
+                position: None
+            })
+        );
+        assert_eq!(
+            "1024x600+10-20".parse(),
+            Ok(Geometry {
+                width: 1024,
+                height: 600,
+                position: Some((10, -20))
+            })
+        );
+        assert!("800x480+10".parse::<Geometry>().is_err());
+        assert!("0x480".parse::<Geometry>().is_err());
+        assert!("800".parse::<Geometry>().is_err());
+    }
+}